@@ -0,0 +1,52 @@
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use rsedlib::{
+    myers::distance_profile,
+    param::{AlignMode, AlignTask, CigarFmt, EdlibAlignParam},
+};
+
+#[path = "../../tests/common/mod.rs"]
+mod common;
+
+// layout: mode, task, k, number of equality pairs, the pairs, query length, then query and target
+fuzz_target!(|data: &[u8]| {
+    if data.len() < 5 {
+        return;
+    }
+    let mode = [
+        AlignMode::Global,
+        AlignMode::Prefix,
+        AlignMode::Infix,
+        AlignMode::Suffix,
+    ][data[0] as usize % 4];
+    let task = [AlignTask::Distance, AlignTask::Locations, AlignTask::Path][data[1] as usize % 3];
+    let k = data[2] as i32 - 128;
+    let num_eq_pairs = (data[3] % 4) as usize;
+    let data = &data[4..];
+    if data.len() < 2 * num_eq_pairs + 1 {
+        return;
+    }
+    let eq_pairs: Vec<(u8, u8)> = data[..2 * num_eq_pairs]
+        .chunks(2)
+        .map(|pair| (pair[0], pair[1]))
+        .collect();
+    let data = &data[2 * num_eq_pairs..];
+    let query_len = data[0] as usize;
+    let seqs = &data[1..];
+    if query_len == 0 || query_len >= seqs.len() {
+        return;
+    }
+    let (query, target) = seqs.split_at(query_len);
+
+    let mut param = EdlibAlignParam::new(k, mode, task);
+    param.set_cigar_fmt(CigarFmt::Extended);
+    param.set_eq_pairs(eq_pairs.clone());
+    if let Err(err) = common::check_against_reference(query, target, &param, &eq_pairs) {
+        panic!("{}", err);
+    }
+    assert_eq!(
+        distance_profile(query, target, &param),
+        common::reference_profile(query, target, mode, &eq_pairs)
+    );
+});
@@ -0,0 +1,114 @@
+use crate::{
+    occurrences::{ApproxOccurrences, Occurrence},
+    param::EdlibAlignParam,
+    utils::parallel_map,
+};
+
+/// Every occurrence of query in a long target (e.g. a chromosome), searched in windows of
+/// chunk_len bases spread over `threads` threads.
+///
+/// Uses k, max error rate, equality pairs and cigar format of `aln_param` like
+/// `ApproxOccurrences::new`; the resulting k must be non-negative.
+/// Windows overlap by query length + k, so every hit lies completely within some window;
+/// chunk_len must be larger than that overlap.
+/// Hits of different windows that overlap in the target are merged into the one with the
+/// smallest edit distance, so a hit cut at a window border does not shadow the complete one.
+/// Returns the hits sorted by position, in coordinates of the whole target.
+pub fn chunked_search(
+    query: &[u8],
+    target: &[u8],
+    chunk_len: usize,
+    threads: usize,
+    aln_param: &EdlibAlignParam,
+) -> Result<Vec<Occurrence>, String> {
+    let k = aln_param.effective_k(query.len());
+    if k < 0 {
+        return Err("chunked search requires a non-negative k".to_string());
+    }
+    let overlap = query.len() + k as usize;
+    if chunk_len <= overlap {
+        return Err(format!(
+            "chunk length {} must be larger than query length + k = {}",
+            chunk_len, overlap
+        ));
+    }
+
+    let step = chunk_len - overlap;
+    let mut windows = Vec::new();
+    let mut start = 0;
+    loop {
+        let end = (start + chunk_len).min(target.len());
+        windows.push((start, end));
+        if end == target.len() {
+            break;
+        }
+        start += step;
+    }
+
+    let hits_per_window = parallel_map(windows.len(), threads, |w| {
+        let (start, end) = windows[w];
+        ApproxOccurrences::new(query, &target[start..end], aln_param)?
+            .map(|hit| {
+                let mut hit = hit?;
+                hit.start += start;
+                hit.end += start;
+                Ok((w, hit))
+            })
+            .collect::<Result<Vec<_>, String>>()
+    })?;
+
+    let mut hits: Vec<(usize, Occurrence)> = hits_per_window.into_iter().flatten().collect();
+    hits.sort_unstable_by_key(|(w, hit)| (hit.start, hit.end, *w));
+
+    let mut merged: Vec<(usize, Occurrence)> = Vec::new();
+    for (w, hit) in hits {
+        match merged.last_mut() {
+            Some((last_w, last)) if *last_w != w && hit.start <= last.end => {
+                if hit.edit_distance < last.edit_distance {
+                    *last_w = w;
+                    *last = hit;
+                }
+            }
+            _ => merged.push((w, hit)),
+        }
+    }
+
+    Ok(merged.into_iter().map(|(_, hit)| hit).collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        occurrences::find_all_within,
+        param::{AlignMode, AlignTask, CigarFmt},
+    };
+
+    #[test]
+    fn test_chunked_search() {
+        let primer = b"GATCGGAAGAGC";
+        let mut genome: Vec<u8> = b"TTAGCACCATTA".repeat(40);
+        // exact copy, copy with a mismatch and copy with a deletion, the last ones across chunk borders
+        genome[10..22].copy_from_slice(primer);
+        genome[95..107].copy_from_slice(b"GATCGGTAGAGC");
+        genome[290..301].copy_from_slice(b"GATCGAAGAGC");
+
+        let mut param = EdlibAlignParam::new(1, AlignMode::Infix, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Extended);
+        let mut expected: Vec<Occurrence> = find_all_within(primer, &genome, 1)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        expected.sort_unstable_by_key(|hit| hit.start);
+        assert_eq!(expected.len(), 3);
+
+        for (chunk_len, threads) in [(20, 1), (30, 3), (100, 4), (1000, 2)] {
+            let hits = chunked_search(primer, &genome, chunk_len, threads, &param).unwrap();
+            assert_eq!(hits, expected, "chunk length {}", chunk_len);
+        }
+
+        assert!(chunked_search(primer, &genome, 13, 1, &param).is_err());
+        param.set_k(-1);
+        assert!(chunked_search(primer, &genome, 100, 1, &param).is_err());
+    }
+}
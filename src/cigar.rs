@@ -0,0 +1,118 @@
+use crate::param::CigarFmt;
+
+/// Alignment operation, numbered like the alignment path returned by edlib.
+/// Insertion and deletion are relative to the target: an insertion consumes a query base only.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AlignOp {
+    Match = 0,
+    Insertion = 1,
+    Deletion = 2,
+    Mismatch = 3,
+}
+
+impl AlignOp {
+    pub fn consumes_query(&self) -> bool {
+        *self != AlignOp::Deletion
+    }
+
+    pub fn consumes_target(&self) -> bool {
+        *self != AlignOp::Insertion
+    }
+}
+
+/// Parse a cigar into (op, length) runs.
+/// Standard format 'M' does not tell matches from mismatches and is reported as `AlignOp::Match`.
+pub fn parse_cigar(cigar: &str) -> Result<Vec<(AlignOp, usize)>, String> {
+    let mut ops = Vec::new();
+    let mut len_start = 0;
+    for (pos, c) in cigar.char_indices() {
+        if c.is_ascii_digit() {
+            continue;
+        }
+        let len = cigar[len_start..pos]
+            .parse::<usize>()
+            .map_err(|_| format!("invalid cigar {:?}: missing length before {:?}", cigar, c))?;
+        let op = match c {
+            '=' | 'M' => AlignOp::Match,
+            'X' => AlignOp::Mismatch,
+            'I' => AlignOp::Insertion,
+            'D' => AlignOp::Deletion,
+            _ => return Err(format!("invalid cigar {:?}: unknown op {:?}", cigar, c)),
+        };
+        ops.push((op, len));
+        len_start = pos + c.len_utf8();
+    }
+    if len_start != cigar.len() {
+        return Err(format!("invalid cigar {:?}: trailing length", cigar));
+    }
+
+    Ok(ops)
+}
+
+/// Build a cigar from a path, None for `CigarFmt::NoCigar`.
+pub fn to_cigar(ops: &[AlignOp], cigar_fmt: CigarFmt) -> Option<String> {
+    let symbol = |op: AlignOp| match (op, cigar_fmt) {
+        (AlignOp::Match | AlignOp::Mismatch, CigarFmt::Standard) => 'M',
+        (AlignOp::Match, _) => '=',
+        (AlignOp::Mismatch, _) => 'X',
+        (AlignOp::Insertion, _) => 'I',
+        (AlignOp::Deletion, _) => 'D',
+    };
+    if cigar_fmt == CigarFmt::NoCigar {
+        return None;
+    }
+
+    let mut cigar = String::new();
+    let mut run: Option<(char, usize)> = None;
+    for &op in ops {
+        run = match run {
+            Some((c, len)) if c == symbol(op) => Some((c, len + 1)),
+            Some((c, len)) => {
+                cigar.push_str(&format!("{}{}", len, c));
+                Some((symbol(op), 1))
+            }
+            None => Some((symbol(op), 1)),
+        };
+    }
+    if let Some((c, len)) = run {
+        cigar.push_str(&format!("{}{}", len, c));
+    }
+    Some(cigar)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_cigar() {
+        assert_eq!(
+            parse_cigar("1D5=1X12I").unwrap(),
+            vec![
+                (AlignOp::Deletion, 1),
+                (AlignOp::Match, 5),
+                (AlignOp::Mismatch, 1),
+                (AlignOp::Insertion, 12)
+            ]
+        );
+        assert!(parse_cigar("").unwrap().is_empty());
+        assert!(parse_cigar("3=2").is_err());
+        assert!(parse_cigar("=").is_err());
+        assert!(parse_cigar("3S").is_err());
+    }
+
+    #[test]
+    fn test_to_cigar() {
+        let ops = [
+            AlignOp::Match,
+            AlignOp::Mismatch,
+            AlignOp::Match,
+            AlignOp::Insertion,
+            AlignOp::Insertion,
+            AlignOp::Deletion,
+        ];
+        assert_eq!(to_cigar(&ops, CigarFmt::Extended).unwrap(), "1=1X1=2I1D");
+        assert_eq!(to_cigar(&ops, CigarFmt::Standard).unwrap(), "3M2I1D");
+        assert_eq!(to_cigar(&ops, CigarFmt::NoCigar), None);
+    }
+}
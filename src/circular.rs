@@ -0,0 +1,127 @@
+use crate::{
+    edlib_align,
+    param::{AlignMode, AlignTask, EdlibAlignParam},
+    EdlibAlignResult,
+};
+
+/// Result of `edlib_align_circular`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CircularAlignResult {
+    /// locations are (start, end) modulo the target length, so end < start for a hit
+    /// spanning the origin
+    pub result: EdlibAlignResult,
+    /// for each location, whether the hit spans the origin of the target
+    pub wraps: Vec<bool>,
+}
+
+/// Align query against a circular target such as a plasmid or mitochondrial genome, so hits
+/// spanning the origin are found.
+///
+/// `aln_param` must use `AlignMode::Infix`. The target is extended by its first
+/// query length + k bases (all of it for a negative k), which is as far as a hit starting
+/// before the origin can reach, and locations found twice are reported once.
+/// Start locations are needed to tell wrapping hits apart, so `AlignTask::Distance` is run
+/// as `AlignTask::Locations`.
+pub fn edlib_align_circular(
+    query: &[u8],
+    target: &[u8],
+    aln_param: &EdlibAlignParam,
+) -> Result<CircularAlignResult, String> {
+    if aln_param.mode() != AlignMode::Infix {
+        return Err(format!(
+            "circular alignment requires AlignMode::Infix, got {:?}",
+            aln_param.mode()
+        ));
+    }
+    if target.is_empty() {
+        return Err("circular alignment requires a non-empty target".to_string());
+    }
+
+    let len = target.len();
+    let k = aln_param.effective_k(query.len());
+    let extension = if k < 0 {
+        len
+    } else {
+        (query.len() + k as usize).min(len)
+    };
+    let extended = [target, &target[..extension]].concat();
+    let mut aln_param = aln_param.clone();
+    if aln_param.task() == AlignTask::Distance {
+        aln_param.set_task(AlignTask::Locations);
+    }
+    let mut result = edlib_align(query, &extended, &aln_param)?;
+
+    // kept in edlib's order, the path belongs to the first location
+    let mut locations = Vec::with_capacity(result.locations.len());
+    let mut wraps = Vec::with_capacity(result.locations.len());
+    for &(start, end) in &result.locations {
+        // the end -1 (whole query deleted) is no position on the circle
+        if end >= extended.len() {
+            continue;
+        }
+        let (location, wrap) = if start >= len {
+            ((start - len, end - len), false)
+        } else {
+            ((start, end % len), end >= len)
+        };
+        if !locations.contains(&location) {
+            locations.push(location);
+            wraps.push(wrap);
+        }
+    }
+    result.locations = locations;
+
+    Ok(CircularAlignResult { result, wraps })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::CigarFmt;
+
+    #[test]
+    fn test_edlib_align_circular() {
+        let plasmid = b"CGGAAGAGCTTTTTTTTTTTTTTTTTTTTGAT";
+        let mut param = EdlibAlignParam::new(-1, AlignMode::Infix, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Extended);
+
+        // the site spans the origin, a linear alignment misses three bases
+        let linear = edlib_align(b"GATCGGAAGAGC", plasmid, &param).unwrap();
+        assert_eq!(linear.edit_distance, 3);
+
+        let aln_res = edlib_align_circular(b"GATCGGAAGAGC", plasmid, &param).unwrap();
+        assert_eq!(aln_res.result.edit_distance, 0);
+        assert_eq!(aln_res.result.locations, vec![(29, 8)]);
+        assert_eq!(aln_res.wraps, vec![true]);
+        assert_eq!(aln_res.result.cigar.as_deref(), Some("12="));
+
+        // a site away from the origin is reported once
+        param.set_k(2);
+        let aln_res = edlib_align_circular(b"GCTTTTT", plasmid, &param).unwrap();
+        assert_eq!(aln_res.result.locations, vec![(7, 13)]);
+        assert_eq!(aln_res.wraps, vec![false]);
+
+        // starts are computed even for the distance task
+        param.set_k(-1);
+        param.set_task(AlignTask::Distance);
+        let aln_res = edlib_align_circular(b"GATCGGAAGAGC", plasmid, &param).unwrap();
+        assert_eq!(aln_res.result.locations, vec![(29, 8)]);
+        assert_eq!(aln_res.wraps, vec![true]);
+
+        // the whole target is repeated for a negative k, the copy of the site is dropped
+        let aln_res = edlib_align_circular(b"GCTTTTT", plasmid, &param).unwrap();
+        assert_eq!(aln_res.result.locations, vec![(7, 13)]);
+        assert_eq!(aln_res.wraps, vec![false]);
+
+        // deleting the whole query is optimal, edlib's end -1 is dropped
+        let aln_res = edlib_align_circular(b"GGG", b"TTTTT", &param).unwrap();
+        assert_eq!(aln_res.result.edit_distance, 3);
+        assert!(!aln_res.result.locations.is_empty());
+        for (&(start, end), &wrap) in aln_res.result.locations.iter().zip(&aln_res.wraps) {
+            assert_eq!(wrap, end < start, "location ({}, {})", start, end);
+        }
+
+        param.set_mode(AlignMode::Global);
+        assert!(edlib_align_circular(b"GATC", plasmid, &param).is_err());
+    }
+}
@@ -0,0 +1,240 @@
+use std::collections::VecDeque;
+
+use crate::{edlib_align, param::EdlibAlignParam};
+
+/// How UMIs within the distance threshold are collapsed.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum UmiMethod {
+    /// UMI-tools' adjacency method: within each connected component of UMIs within the
+    /// threshold, the most abundant UMIs are taken as leads until they and their neighbours
+    /// cover the component. Every lead forms a cluster, and the other UMIs join the most
+    /// abundant lead they are adjacent to.
+    Adjacency,
+    /// UMI-tools' directional method: UMI a absorbs b if they are within the threshold and
+    /// count(a) >= 2 * count(b) - 1, transitively along such edges.
+    Directional,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clustering {
+    /// cluster id of every input sequence
+    pub assignments: Vec<usize>,
+    /// input index of the representative of every cluster
+    pub representatives: Vec<usize>,
+}
+
+impl Clustering {
+    pub fn num_clusters(&self) -> usize {
+        self.representatives.len()
+    }
+
+    fn new(size: usize) -> Self {
+        Clustering {
+            assignments: vec![usize::MAX; size],
+            representatives: Vec::new(),
+        }
+    }
+
+    fn is_assigned(&self, idx: usize) -> bool {
+        self.assignments[idx] != usize::MAX
+    }
+
+    fn new_cluster(&mut self, representative: usize) -> usize {
+        self.representatives.push(representative);
+        self.assignments[representative] = self.representatives.len() - 1;
+        self.representatives.len() - 1
+    }
+}
+
+/// Global edit distance if it is within threshold.
+fn distance_within(a: &[u8], b: &[u8], threshold: u32) -> Result<Option<u32>, String> {
+    let mut aln_param = EdlibAlignParam::default();
+    aln_param.set_k(threshold as i32);
+    let dist = edlib_align(a, b, &aln_param)?.edit_distance;
+    Ok((dist >= 0).then_some(dist as u32))
+}
+
+fn neighbours(seqs: &[&[u8]], threshold: u32) -> Result<Vec<Vec<usize>>, String> {
+    let mut neighbours = vec![Vec::new(); seqs.len()];
+    for i in 0..seqs.len() {
+        for j in (i + 1)..seqs.len() {
+            if distance_within(seqs[i], seqs[j], threshold)?.is_some() {
+                neighbours[i].push(j);
+                neighbours[j].push(i);
+            }
+        }
+    }
+    Ok(neighbours)
+}
+
+/// Collapse UMIs with their read counts into clusters. UMIs should be distinct.
+/// Representatives are the most abundant UMI of each cluster.
+pub fn collapse_umis(
+    umis: &[&[u8]],
+    counts: &[usize],
+    threshold: u32,
+    method: UmiMethod,
+) -> Result<Clustering, String> {
+    if umis.len() != counts.len() {
+        return Err(format!(
+            "got {} umis but {} counts",
+            umis.len(),
+            counts.len()
+        ));
+    }
+
+    let neighbours = neighbours(umis, threshold)?;
+    let mut by_count: Vec<usize> = (0..umis.len()).collect();
+    by_count.sort_by_key(|&idx| std::cmp::Reverse(counts[idx]));
+
+    let mut clustering = Clustering::new(umis.len());
+    match method {
+        UmiMethod::Adjacency => adjacency(&by_count, &neighbours, &mut clustering),
+        UmiMethod::Directional => directional(&by_count, &neighbours, counts, &mut clustering),
+    }
+
+    Ok(clustering)
+}
+
+fn adjacency(by_count: &[usize], neighbours: &[Vec<usize>], clustering: &mut Clustering) {
+    let mut rank = vec![0; by_count.len()];
+    for (r, &idx) in by_count.iter().enumerate() {
+        rank[idx] = r;
+    }
+
+    let mut in_component = vec![false; by_count.len()];
+    let mut covered = vec![false; by_count.len()];
+    for &seed in by_count {
+        if in_component[seed] {
+            continue;
+        }
+        in_component[seed] = true;
+        let mut component = vec![seed];
+        let mut next_node = 0;
+        while let Some(&node) = component.get(next_node) {
+            next_node += 1;
+            for &next in &neighbours[node] {
+                if !in_component[next] {
+                    in_component[next] = true;
+                    component.push(next);
+                }
+            }
+        }
+        component.sort_unstable_by_key(|&idx| rank[idx]);
+
+        // fewest most abundant leads whose neighbourhoods cover the component
+        let mut num_covered = 0;
+        let mut num_leads = 0;
+        while num_covered < component.len() {
+            let lead = component[num_leads];
+            num_leads += 1;
+            for &idx in std::iter::once(&lead).chain(&neighbours[lead]) {
+                if !covered[idx] {
+                    covered[idx] = true;
+                    num_covered += 1;
+                }
+            }
+        }
+
+        let leads = &component[..num_leads];
+        let clusters: Vec<usize> = leads
+            .iter()
+            .map(|&lead| clustering.new_cluster(lead))
+            .collect();
+        for (&lead, cluster) in leads.iter().zip(clusters) {
+            for &next in &neighbours[lead] {
+                if !clustering.is_assigned(next) {
+                    clustering.assignments[next] = cluster;
+                }
+            }
+        }
+    }
+}
+
+fn directional(
+    by_count: &[usize],
+    neighbours: &[Vec<usize>],
+    counts: &[usize],
+    clustering: &mut Clustering,
+) {
+    for &seed in by_count {
+        if clustering.is_assigned(seed) {
+            continue;
+        }
+        let cluster = clustering.new_cluster(seed);
+        let mut queue = VecDeque::from([seed]);
+        while let Some(node) = queue.pop_front() {
+            for &next in &neighbours[node] {
+                if !clustering.is_assigned(next) && counts[node] + 1 >= 2 * counts[next] {
+                    clustering.assignments[next] = cluster;
+                    queue.push_back(next);
+                }
+            }
+        }
+    }
+}
+
+/// Greedy centroid clustering: sequences are visited in input order and join the closest
+/// existing centroid within threshold, or become a new centroid.
+/// Sort the input (e.g. by abundance or length) to control which sequences become centroids.
+pub fn greedy_cluster(seqs: &[&[u8]], threshold: u32) -> Result<Clustering, String> {
+    let mut clustering = Clustering::new(seqs.len());
+    for (idx, seq) in seqs.iter().enumerate() {
+        let mut best: Option<(u32, usize)> = None;
+        for (cluster, &centroid) in clustering.representatives.iter().enumerate() {
+            // only a strictly closer centroid can replace the current best
+            let max_dist = match best {
+                Some((0, _)) => break,
+                Some((dist, _)) => dist - 1,
+                None => threshold,
+            };
+            if let Some(dist) = distance_within(seq, seqs[centroid], max_dist)? {
+                best = Some((dist, cluster));
+            }
+        }
+
+        match best {
+            Some((_, cluster)) => clustering.assignments[idx] = cluster,
+            None => {
+                clustering.new_cluster(idx);
+            }
+        }
+    }
+
+    Ok(clustering)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_collapse_umis() {
+        let umis: [&[u8]; 5] = [b"AAAAAA", b"AAAAAT", b"AAAATT", b"GGGGGG", b"GGGGGC"];
+        let counts = [100, 40, 20, 10, 9];
+
+        let directional = collapse_umis(&umis, &counts, 1, UmiMethod::Directional).unwrap();
+        assert_eq!(directional.assignments, vec![0, 0, 0, 1, 2]);
+        assert_eq!(directional.representatives, vec![0, 3, 4]);
+
+        // AAAAAA alone does not cover AAAATT, so AAAAAT is a lead as well and takes it
+        let adjacency = collapse_umis(&umis, &counts, 1, UmiMethod::Adjacency).unwrap();
+        assert_eq!(adjacency.assignments, vec![0, 1, 1, 2, 2]);
+        assert_eq!(adjacency.representatives, vec![0, 1, 3]);
+
+        // a hub covers its whole star with one lead, however far apart the leaves are
+        let umis: [&[u8]; 4] = [b"AAAAAT", b"AAAAAA", b"TAAAAA", b"AATAAA"];
+        let adjacency = collapse_umis(&umis, &[10, 30, 5, 5], 1, UmiMethod::Adjacency).unwrap();
+        assert_eq!(adjacency.assignments, vec![0, 0, 0, 0]);
+        assert_eq!(adjacency.representatives, vec![1]);
+    }
+
+    #[test]
+    fn test_greedy_cluster() {
+        let seqs: [&[u8]; 4] = [b"ACGTACGT", b"TTTTTTTT", b"ACGTACGA", b"TTTTTTTA"];
+        let clustering = greedy_cluster(&seqs, 2).unwrap();
+        assert_eq!(clustering.assignments, vec![0, 1, 0, 1]);
+        assert_eq!(clustering.representatives, vec![0, 1]);
+        assert_eq!(clustering.num_clusters(), 2);
+    }
+}
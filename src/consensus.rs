@@ -0,0 +1,207 @@
+use std::collections::HashMap;
+
+use crate::{
+    cigar::{parse_cigar, AlignOp},
+    edlib_align,
+    param::{AlignTask, CigarFmt, EdlibAlignParam},
+};
+
+const GAP: u8 = b'-';
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Consensus {
+    pub sequence: Vec<u8>,
+    /// number of reads voting for each consensus base
+    pub support: Vec<u32>,
+    /// number of reads covering each consensus base
+    pub coverage: Vec<u32>,
+}
+
+/// Accumulates per-column votes of reads aligned to a draft backbone and calls a majority consensus.
+///
+/// Every backbone position collects base or deletion votes, every gap between two backbone positions
+/// collects the inserted sequences of the reads spanning it.
+pub struct ConsensusBuilder {
+    backbone: Vec<u8>,
+    aln_param: EdlibAlignParam,
+    /// (symbol, count) votes per backbone position, GAP for deletions
+    votes: Vec<Vec<(u8, u32)>>,
+    coverage: Vec<u32>,
+    /// inserted sequences before each backbone position
+    insertions: Vec<HashMap<Vec<u8>, u32>>,
+    /// reads spanning the gap before each backbone position
+    insertion_coverage: Vec<u32>,
+    num_reads: usize,
+}
+
+impl ConsensusBuilder {
+    /// Reads are aligned as query against the backbone with the mode, k and equality pairs of `aln_param`.
+    /// Use `AlignMode::Infix` if reads may cover only part of the backbone.
+    pub fn new(backbone: &[u8], aln_param: EdlibAlignParam) -> Self {
+        let mut aln_param = aln_param;
+        aln_param.set_task(AlignTask::Path);
+        aln_param.set_cigar_fmt(CigarFmt::Extended);
+        ConsensusBuilder {
+            backbone: backbone.to_vec(),
+            aln_param,
+            votes: vec![Vec::new(); backbone.len()],
+            coverage: vec![0; backbone.len()],
+            insertions: vec![HashMap::new(); backbone.len()],
+            insertion_coverage: vec![0; backbone.len()],
+            num_reads: 0,
+        }
+    }
+
+    pub fn num_reads(&self) -> usize {
+        self.num_reads
+    }
+
+    /// Align read to the backbone and add its votes.
+    /// Returns false if the read does not align within k and was skipped.
+    /// Fails if the backbone is empty, as there is nothing to vote on.
+    pub fn add_read(&mut self, read: &[u8]) -> Result<bool, String> {
+        if self.backbone.is_empty() {
+            return Err("can not add reads to an empty backbone".to_string());
+        }
+        let aln_res = edlib_align(read, &self.backbone, &self.aln_param)?;
+        if aln_res.edit_distance < 0 || aln_res.locations.is_empty() {
+            return Ok(false);
+        }
+        let ops = parse_cigar(aln_res.cigar.as_deref().unwrap_or_default())?;
+        let (start, end) = aln_res.locations[0];
+
+        let mut query_pos = 0;
+        let mut target_pos = start;
+        let mut inserted = Vec::new();
+        for (op, len) in ops {
+            for _ in 0..len {
+                if op == AlignOp::Insertion {
+                    inserted.push(read[query_pos]);
+                    query_pos += 1;
+                    continue;
+                }
+                // insertions before the first aligned backbone base are not spanned by the read
+                if !inserted.is_empty() && target_pos > start {
+                    *self.insertions[target_pos]
+                        .entry(std::mem::take(&mut inserted))
+                        .or_insert(0) += 1;
+                }
+                inserted.clear();
+
+                let symbol = if op == AlignOp::Deletion {
+                    GAP
+                } else {
+                    query_pos += 1;
+                    read[query_pos - 1]
+                };
+                add_vote(&mut self.votes[target_pos], symbol);
+                self.coverage[target_pos] += 1;
+                target_pos += 1;
+            }
+        }
+        for pos in (start + 1)..=end.min(self.backbone.len() - 1) {
+            self.insertion_coverage[pos] += 1;
+        }
+
+        self.num_reads += 1;
+        Ok(true)
+    }
+
+    /// Majority vote over the reads added so far.
+    /// Backbone positions without coverage keep the backbone base with zero support.
+    pub fn consensus(&self) -> Consensus {
+        let mut consensus = Consensus {
+            sequence: Vec::with_capacity(self.backbone.len()),
+            support: Vec::with_capacity(self.backbone.len()),
+            coverage: Vec::with_capacity(self.backbone.len()),
+        };
+
+        for pos in 0..self.backbone.len() {
+            let spanning = self.insertion_coverage[pos];
+            if let Some((inserted, &cnt)) = self.insertions[pos]
+                .iter()
+                .max_by(|a, b| a.1.cmp(b.1).then_with(|| b.0.cmp(a.0)))
+            {
+                if 2 * cnt > spanning {
+                    consensus.sequence.extend_from_slice(inserted);
+                    consensus
+                        .support
+                        .extend(std::iter::repeat_n(cnt, inserted.len()));
+                    consensus
+                        .coverage
+                        .extend(std::iter::repeat_n(spanning, inserted.len()));
+                }
+            }
+
+            let backbone_base = self.backbone[pos];
+            let winner = self.votes[pos].iter().max_by(|a, b| {
+                a.1.cmp(&b.1)
+                    .then_with(|| (a.0 == backbone_base).cmp(&(b.0 == backbone_base)))
+                    .then_with(|| b.0.cmp(&a.0))
+            });
+            match winner {
+                Some(&(GAP, _)) => {}
+                Some(&(base, cnt)) => {
+                    consensus.sequence.push(base);
+                    consensus.support.push(cnt);
+                    consensus.coverage.push(self.coverage[pos]);
+                }
+                None => {
+                    consensus.sequence.push(backbone_base);
+                    consensus.support.push(0);
+                    consensus.coverage.push(0);
+                }
+            }
+        }
+
+        consensus
+    }
+}
+
+fn add_vote(votes: &mut Vec<(u8, u32)>, symbol: u8) {
+    match votes.iter_mut().find(|(s, _)| *s == symbol) {
+        Some((_, cnt)) => *cnt += 1,
+        None => votes.push((symbol, 1)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::AlignMode;
+
+    #[test]
+    fn test_consensus_polishes_backbone() {
+        // the backbone has a substitution (C>G at 4), a spurious base (T at 8) and misses an A after 12
+        let backbone = b"ACGTGATCTGGACTTGCA";
+        let truth = b"ACGTCATCGGACATTGCA";
+        let reads: [&[u8]; 4] = [
+            truth,
+            b"ACGTCATCGGACATTGCA",
+            b"ACGTCATCGGACATTCA",
+            b"ACGTCATC",
+        ];
+
+        let mut param = EdlibAlignParam::default();
+        param.set_mode(AlignMode::Infix);
+        let mut builder = ConsensusBuilder::new(backbone, param);
+        for read in reads {
+            assert!(builder.add_read(read).unwrap());
+        }
+
+        let consensus = builder.consensus();
+        assert_eq!(builder.num_reads(), 4);
+        assert_eq!(consensus.sequence, truth);
+        assert_eq!(consensus.support[4], 4);
+        assert_eq!(consensus.coverage[0], 4);
+        assert_eq!(consensus.coverage[truth.len() - 1], 3);
+        assert_eq!(consensus.support.len(), truth.len());
+    }
+
+    #[test]
+    fn test_consensus_empty_backbone() {
+        let mut builder = ConsensusBuilder::new(b"", EdlibAlignParam::default());
+        assert!(builder.add_read(b"ACGT").is_err());
+        assert!(builder.consensus().sequence.is_empty());
+    }
+}
@@ -0,0 +1,353 @@
+use crate::{
+    cigar::{to_cigar, AlignOp},
+    param::{AlignMode, AlignTask, EdlibAlignParam},
+    EdlibAlignResult,
+};
+
+/// Costs minimized by `dp_align` and summed up by `rescore::rescore`, lower is better.
+/// Aligning a with b costs `substitution(a, b)`, a gap of length l costs `gap_open + l * gap_extend`.
+/// The default is unit edit distance.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Scoring {
+    substitution: Vec<i32>,
+    pub gap_open: i32,
+    pub gap_extend: i32,
+}
+
+impl Scoring {
+    /// match_cost for equal bytes, mismatch_cost for everything else.
+    pub fn new(match_cost: i32, mismatch_cost: i32, gap_open: i32, gap_extend: i32) -> Self {
+        let mut substitution = vec![mismatch_cost; 256 * 256];
+        for c in 0..256 {
+            substitution[c * 256 + c] = match_cost;
+        }
+        Scoring {
+            substitution,
+            gap_open,
+            gap_extend,
+        }
+    }
+
+    /// Set the cost of a against b and of b against a, e.g. to make transitions cheaper.
+    pub fn set_substitution(&mut self, a: u8, b: u8, cost: i32) {
+        self.substitution[a as usize * 256 + b as usize] = cost;
+        self.substitution[b as usize * 256 + a as usize] = cost;
+    }
+
+    pub fn substitution(&self, a: u8, b: u8) -> i32 {
+        self.substitution[a as usize * 256 + b as usize]
+    }
+}
+
+impl Default for Scoring {
+    fn default() -> Self {
+        Scoring::new(0, 1, 0, 1)
+    }
+}
+
+const INF: i32 = i32::MAX / 2;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum State {
+    /// query and target base aligned
+    Diag,
+    /// query base against a gap
+    Ins,
+    /// target base against a gap
+    Del,
+}
+
+/// Gotoh matrices, (query len + 1) x (target len + 1) each.
+struct Matrices {
+    cols: usize,
+    diag: Vec<i32>,
+    ins: Vec<i32>,
+    del: Vec<i32>,
+}
+
+impl Matrices {
+    fn get(&self, state: State, i: usize, j: usize) -> i32 {
+        let idx = i * self.cols + j;
+        match state {
+            State::Diag => self.diag[idx],
+            State::Ins => self.ins[idx],
+            State::Del => self.del[idx],
+        }
+    }
+
+    fn best(&self, i: usize, j: usize) -> (i32, State) {
+        [State::Diag, State::Ins, State::Del]
+            .into_iter()
+            .map(|state| (self.get(state, i, j), state))
+            .min_by_key(|&(cost, _)| cost)
+            .unwrap()
+    }
+}
+
+/// Plain O(nm) time and memory aligner with affine gap costs and the same mode semantics as edlib.
+///
+/// Honors mode, task, k, cigar format and equality pairs of `aln_param` (a pair made equal costs
+/// like a match of the query base), and returns the
+/// total cost in `edit_distance` (-1 if it is over a non-negative k, which is capped by the max
+/// error rate as in `edlib_align`).
+/// One location is reported per optimal end, so suffix alignments have a single location.
+/// With the default `Scoring` results agree with `edlib_align`, though co-optimal paths may differ.
+pub fn dp_align(
+    query: &[u8],
+    target: &[u8],
+    aln_param: &EdlibAlignParam,
+    scoring: &Scoring,
+) -> Result<EdlibAlignResult, String> {
+    if scoring.gap_extend < 0 || scoring.gap_open < 0 {
+        return Err("gap costs must be non-negative".to_string());
+    }
+
+    let equal = aln_param.equality_table();
+
+    let free_start = matches!(aln_param.mode(), AlignMode::Infix | AlignMode::Suffix);
+    let matrices = fill(query, target, free_start, scoring, &equal);
+
+    let (n, m) = (query.len(), target.len());
+    let end_cols: Vec<usize> = match aln_param.mode() {
+        AlignMode::Global | AlignMode::Suffix => vec![m],
+        AlignMode::Prefix | AlignMode::Infix if m == 0 => vec![0],
+        AlignMode::Prefix | AlignMode::Infix => (1..=m).collect(),
+    };
+    let best = end_cols
+        .iter()
+        .map(|&j| matrices.best(n, j).0)
+        .min()
+        .unwrap();
+
+    let mut alphabet = [false; 256];
+    query
+        .iter()
+        .chain(target)
+        .for_each(|&c| alphabet[c as usize] = true);
+    let mut aln_res = EdlibAlignResult {
+        edit_distance: best,
+        alphabet_length: alphabet.iter().filter(|&&seen| seen).count() as i32,
+        locations: Vec::new(),
+        cigar: None,
+        within_error_rate: None,
+    };
+    let k = aln_param.effective_k(query.len());
+    if k >= 0 && best > k {
+        aln_res.edit_distance = -1;
+        aln_res.within_error_rate = aln_param.within_error_rate(-1, query.len());
+        return Ok(aln_res);
+    }
+    aln_res.within_error_rate = aln_param.within_error_rate(best, query.len());
+
+    for j in end_cols {
+        if matrices.best(n, j).0 != best {
+            continue;
+        }
+        let end = j.saturating_sub(1);
+        if aln_param.task() == AlignTask::Distance {
+            aln_res.locations.push((0, end));
+            continue;
+        }
+
+        let (start, ops) = traceback(&matrices, query, target, j, free_start, scoring, &equal);
+        if aln_res.locations.is_empty() && aln_param.task() == AlignTask::Path {
+            aln_res.cigar = to_cigar(&ops, aln_param.cigar_fmt());
+        }
+        aln_res.locations.push((start, end));
+    }
+
+    Ok(aln_res)
+}
+
+fn substitution(scoring: &Scoring, equal: &[[bool; 256]; 256], q: u8, t: u8) -> i32 {
+    if equal[q as usize][t as usize] {
+        scoring.substitution(q, q)
+    } else {
+        scoring.substitution(q, t)
+    }
+}
+
+fn fill(
+    query: &[u8],
+    target: &[u8],
+    free_start: bool,
+    scoring: &Scoring,
+    equal: &[[bool; 256]; 256],
+) -> Matrices {
+    let cols = target.len() + 1;
+    let size = (query.len() + 1) * cols;
+    let mut mat = Matrices {
+        cols,
+        diag: vec![INF; size],
+        ins: vec![INF; size],
+        del: vec![INF; size],
+    };
+    let gap = |len: usize| scoring.gap_open + len as i32 * scoring.gap_extend;
+
+    mat.diag[0] = 0;
+    for j in 1..cols {
+        if free_start {
+            mat.diag[j] = 0;
+        } else {
+            mat.del[j] = gap(j);
+        }
+    }
+    for i in 1..=query.len() {
+        mat.ins[i * cols] = gap(i);
+    }
+
+    let open = scoring.gap_open + scoring.gap_extend;
+    for i in 1..=query.len() {
+        for j in 1..cols {
+            let up = (i - 1) * cols + j;
+            let left = i * cols + j - 1;
+            let diag = up - 1;
+            let sub = substitution(scoring, equal, query[i - 1], target[j - 1]);
+
+            let idx = i * cols + j;
+            mat.diag[idx] = mat.diag[diag].min(mat.ins[diag]).min(mat.del[diag]) + sub;
+            mat.ins[idx] = (mat.diag[up] + open)
+                .min(mat.ins[up] + scoring.gap_extend)
+                .min(mat.del[up] + open);
+            mat.del[idx] = (mat.diag[left] + open)
+                .min(mat.del[left] + scoring.gap_extend)
+                .min(mat.ins[left] + open);
+        }
+    }
+
+    mat
+}
+
+/// Walk back from (query len, end_col) and return the start column and the path.
+fn traceback(
+    mat: &Matrices,
+    query: &[u8],
+    target: &[u8],
+    end_col: usize,
+    free_start: bool,
+    scoring: &Scoring,
+    equal: &[[bool; 256]; 256],
+) -> (usize, Vec<AlignOp>) {
+    let (mut i, mut j) = (query.len(), end_col);
+    let (_, mut state) = mat.best(i, j);
+    let mut ops = Vec::new();
+    let open = scoring.gap_open + scoring.gap_extend;
+
+    while i > 0 || (j > 0 && !free_start) {
+        let cost = mat.get(state, i, j);
+        // predecessor state whose cost plus the transition reproduces cost
+        let from = |i: usize, j: usize, same: State, step: i32| {
+            [State::Diag, State::Ins, State::Del]
+                .into_iter()
+                .find(|&prev| {
+                    let trans = if prev == same || same == State::Diag {
+                        step
+                    } else {
+                        step - scoring.gap_extend + open
+                    };
+                    mat.get(prev, i, j) + trans == cost
+                })
+                .unwrap()
+        };
+
+        match state {
+            State::Diag => {
+                let is_equal = equal[query[i - 1] as usize][target[j - 1] as usize];
+                let sub = substitution(scoring, equal, query[i - 1], target[j - 1]);
+                ops.push(if is_equal {
+                    AlignOp::Match
+                } else {
+                    AlignOp::Mismatch
+                });
+                state = from(i - 1, j - 1, State::Diag, sub);
+                i -= 1;
+                j -= 1;
+            }
+            State::Ins => {
+                ops.push(AlignOp::Insertion);
+                state = if i == 1 && j == 0 {
+                    State::Diag
+                } else {
+                    from(i - 1, j, State::Ins, scoring.gap_extend)
+                };
+                i -= 1;
+            }
+            State::Del => {
+                ops.push(AlignOp::Deletion);
+                state = if i == 0 && j == 1 {
+                    State::Diag
+                } else {
+                    from(i, j - 1, State::Del, scoring.gap_extend)
+                };
+                j -= 1;
+            }
+        }
+    }
+
+    ops.reverse();
+    (j, ops)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edlib_align, param::CigarFmt};
+
+    #[test]
+    fn test_dp_align_unit_cost_matches_edlib() {
+        let pairs: [(&[u8], &[u8]); 3] = [
+            (b"elephant", b"telephone"),
+            (b"ACGTACGTTGCA", b"TTACGTTCGTTGCAAA"),
+            (b"AAAA", b"CCCCCC"),
+        ];
+        for mode in [
+            AlignMode::Global,
+            AlignMode::Prefix,
+            AlignMode::Infix,
+            AlignMode::Suffix,
+        ] {
+            let mut param = EdlibAlignParam::new(-1, mode, AlignTask::Locations);
+            param.add_eq_pair((b'N', b'A'));
+            for (query, target) in pairs {
+                let dp_res = dp_align(query, target, &param, &Scoring::default()).unwrap();
+                let edlib_res = edlib_align(query, target, &param).unwrap();
+                assert_eq!(dp_res.edit_distance, edlib_res.edit_distance);
+                // edlib also reports end -1 when deleting the whole query is optimal
+                let mut edlib_ends: Vec<usize> = edlib_res
+                    .locations
+                    .iter()
+                    .map(|l| l.1)
+                    .filter(|&end| end < target.len())
+                    .collect();
+                edlib_ends.dedup();
+                assert_eq!(
+                    dp_res.locations.iter().map(|l| l.1).collect::<Vec<_>>(),
+                    edlib_ends
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_dp_align_affine_gap() {
+        let mut param = EdlibAlignParam::new(-1, AlignMode::Global, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Extended);
+        let scoring = Scoring::new(0, 4, 5, 1);
+
+        // one gap of 4 (cost 9) instead of two gaps of 2 (cost 14)
+        let aln_res = dp_align(b"ACGTTTTACGAC", b"ACGACGAC", &param, &scoring).unwrap();
+        assert_eq!(aln_res.edit_distance, 9);
+        assert!(aln_res.cigar.unwrap().contains("4I"));
+
+        param.set_mode(AlignMode::Infix);
+        let aln_res = dp_align(b"CCCCGGGG", b"AAACCCCTTGGGGAAA", &param, &scoring).unwrap();
+        assert_eq!(aln_res.edit_distance, 7);
+        assert_eq!(aln_res.locations, vec![(3, 12)]);
+        assert_eq!(aln_res.cigar.as_deref(), Some("4=2D4="));
+
+        param.set_k(6);
+        let aln_res = dp_align(b"CCCCGGGG", b"AAACCCCTTGGGGAAA", &param, &scoring).unwrap();
+        assert_eq!(aln_res.edit_distance, -1);
+        assert!(aln_res.locations.is_empty());
+    }
+}
@@ -0,0 +1,137 @@
+use std::{collections::HashMap, hash::Hash};
+
+use crate::{edlib_align, param::EdlibAlignParam, EdlibAlignResult};
+
+/// edlib aligns bytes, so query and target together can use at most 256 distinct symbols.
+pub const MAX_ALPHABET_SIZE: usize = 256;
+
+/// Remap the symbols of query and target to bytes that can be handed to edlib.
+/// Symbols are numbered in order of first appearance, query first.
+/// Fails if the combined alphabet has more than `MAX_ALPHABET_SIZE` symbols.
+pub fn remap_symbols<T: Eq + Hash>(
+    query: &[T],
+    target: &[T],
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    remap_with_codes(query, target, &mut HashMap::new())
+}
+
+/// `remap_symbols`, filling codes with the code of every symbol.
+fn remap_with_codes<'a, T: Eq + Hash>(
+    query: &'a [T],
+    target: &'a [T],
+    codes: &mut HashMap<&'a T, usize>,
+) -> Result<(Vec<u8>, Vec<u8>), String> {
+    let query = encode(query, codes);
+    let target = encode(target, codes);
+    if codes.len() > MAX_ALPHABET_SIZE {
+        return Err(format!(
+            "alphabet of query and target has {} distinct symbols, edlib supports at most {}",
+            codes.len(),
+            MAX_ALPHABET_SIZE
+        ));
+    }
+
+    Ok((query, target))
+}
+
+fn encode<'a, T: Eq + Hash>(seq: &'a [T], codes: &mut HashMap<&'a T, usize>) -> Vec<u8> {
+    seq.iter()
+        .map(|symbol| {
+            let next_code = codes.len();
+            // codes past 255 only survive until remap_symbols rejects the alphabet
+            *codes.entry(symbol).or_insert(next_code) as u8
+        })
+        .collect()
+}
+
+/// Align sequences of arbitrary symbols (tokens, words, u16 codes, ...) with edlib.
+/// Locations and cigar are expressed in symbols, not bytes.
+/// Additional equality pairs of `aln_param` are defined on raw bytes, which have no meaning
+/// after remapping, so they are rejected; use `edlib_align_generic_with_eq_pairs` instead.
+pub fn edlib_align_generic<T: Eq + Hash>(
+    query: &[T],
+    target: &[T],
+    aln_param: &EdlibAlignParam,
+) -> Result<EdlibAlignResult, String> {
+    edlib_align_generic_with_eq_pairs(query, target, &[], aln_param)
+}
+
+/// `edlib_align_generic` with additional equality pairs defined on symbols.
+/// Pairs with a symbol that occurs in neither sequence have no effect and are skipped.
+pub fn edlib_align_generic_with_eq_pairs<T: Eq + Hash>(
+    query: &[T],
+    target: &[T],
+    eq_pairs: &[(T, T)],
+    aln_param: &EdlibAlignParam,
+) -> Result<EdlibAlignResult, String> {
+    if !aln_param.additional_eq_pairs().is_empty() {
+        return Err(
+            "byte equality pairs do not apply to remapped symbols, pass symbol pairs instead"
+                .to_string(),
+        );
+    }
+
+    let mut codes = HashMap::new();
+    let (query, target) = remap_with_codes(query, target, &mut codes)?;
+    let mut aln_param = aln_param.clone();
+    aln_param.set_eq_pairs(
+        eq_pairs
+            .iter()
+            .filter_map(|(a, b)| Some((*codes.get(a)? as u8, *codes.get(b)? as u8)))
+            .collect(),
+    );
+    edlib_align(&query, &target, &aln_param)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::AlignMode;
+
+    #[test]
+    fn test_edlib_align_generic_tokens() {
+        let query = ["the", "quick", "brown", "fox"];
+        let target = ["a", "quick", "brown", "dog", "jumps"];
+
+        let mut param = EdlibAlignParam::default();
+        param.set_mode(AlignMode::Infix);
+        let aln_res = edlib_align_generic(&query, &target, &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 2);
+        assert_eq!(aln_res.alphabet_length, 7);
+    }
+
+    #[test]
+    fn test_edlib_align_generic_eq_pairs() {
+        let query = ["the", "quick", "brown", "fox"];
+        let target = ["the", "fast", "brown", "fox"];
+        let param = EdlibAlignParam::default();
+        assert_eq!(
+            edlib_align_generic_with_eq_pairs(&query, &target, &[("fast", "quick")], &param)
+                .unwrap()
+                .edit_distance,
+            0
+        );
+        // pairs on symbols that do not occur are skipped
+        assert_eq!(
+            edlib_align_generic_with_eq_pairs(&query, &target, &[("slow", "quick")], &param)
+                .unwrap()
+                .edit_distance,
+            1
+        );
+
+        let mut byte_param = param.clone();
+        byte_param.add_eq_pair((b'A', b'a'));
+        assert!(edlib_align_generic(&query, &target, &byte_param).is_err());
+    }
+
+    #[test]
+    fn test_remap_symbols_alphabet_too_large() {
+        let query: Vec<u16> = (0..200).collect();
+        let target: Vec<u16> = (100..300).collect();
+        assert!(remap_symbols(&query, &target).is_err());
+
+        let target: Vec<u16> = (100..256).collect();
+        let (query, target) = remap_symbols(&query, &target).unwrap();
+        assert_eq!(query[100], target[0]);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::{
+    align_raw, first_of_adjacent_run,
+    param::{AlignMode, CigarFmt, EdlibAlignParam},
+    AlignResultGuard, EdlibAlignResult,
+};
+
+/// Alignment result that keeps the buffers edlib allocated and reads them on demand,
+/// instead of copying locations and building a cigar up front.
+#[derive(Debug)]
+pub struct LazyAlignResult {
+    raw: AlignResultGuard,
+    within_error_rate: Option<bool>,
+    collapse_locations: bool,
+}
+
+impl LazyAlignResult {
+    /// -1 if k is non-negative and the edit distance is larger than k.
+    pub fn edit_distance(&self) -> i32 {
+        self.raw.editDistance
+    }
+
+    pub fn alphabet_length(&self) -> i32 {
+        self.raw.alphabetLength
+    }
+
+    /// Whether the alignment satisfies the max error rate of the param, None if none was set.
+    pub fn within_error_rate(&self) -> Option<bool> {
+        self.within_error_rate
+    }
+
+    /// Zero-based end positions of optimal alignments in target.
+    pub fn end_locations(&self) -> &[i32] {
+        unsafe { raw_slice(self.raw.endLocations, self.raw.numLocations) }
+    }
+
+    /// Zero-based start positions matching `end_locations`,
+    /// None unless the task is `AlignTask::Locations` or `AlignTask::Path`.
+    pub fn start_locations(&self) -> Option<&[i32]> {
+        if self.raw.startLocations.is_null() {
+            None
+        } else {
+            Some(unsafe { raw_slice(self.raw.startLocations, self.raw.numLocations) })
+        }
+    }
+
+    /// (start, end) pairs like `EdlibAlignResult::locations`, start is 0 if it was not computed.
+    /// Collapsed like in `edlib_align` if the param asked for it, unlike the raw
+    /// `start_locations` and `end_locations`.
+    pub fn locations(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        let starts = self.start_locations();
+        let mut keep = first_of_adjacent_run();
+        let collapse = self.collapse_locations;
+        self.end_locations()
+            .iter()
+            .enumerate()
+            .map(move |(i, &end)| (starts.map_or(0, |starts| starts[i] as usize), end as usize))
+            .filter(move |location| !collapse || keep(location))
+    }
+
+    /// Alignment path of the first location in edlib's numbering (see `cigar::AlignOp`),
+    /// None unless the task is `AlignTask::Path`.
+    pub fn path(&self) -> Option<&[u8]> {
+        self.raw.path()
+    }
+
+    /// Build the cigar of `path`, None if there is no path or cigar_fmt is `CigarFmt::NoCigar`.
+    pub fn cigar(&self, cigar_fmt: CigarFmt) -> Option<String> {
+        self.raw.cigar(cigar_fmt)
+    }
+
+    /// Copy into an owned result, the cigar is built in cigar_fmt.
+    pub fn to_result(&self, cigar_fmt: CigarFmt) -> EdlibAlignResult {
+        EdlibAlignResult {
+            edit_distance: self.edit_distance(),
+            alphabet_length: self.alphabet_length(),
+            locations: self.locations().collect(),
+            cigar: self.cigar(cigar_fmt),
+            within_error_rate: self.within_error_rate,
+        }
+    }
+}
+
+/// An empty slice for null or non-positive length, since edlib leaves unused buffers null.
+unsafe fn raw_slice<'a, T>(ptr: *const T, len: i32) -> &'a [T] {
+    if ptr.is_null() || len <= 0 {
+        &[]
+    } else {
+        std::slice::from_raw_parts(ptr, len as usize)
+    }
+}
+
+/// Same as `edlib_align`, without copying locations or building a cigar.
+/// The cigar format of `aln_param` is ignored, pass one to `LazyAlignResult::cigar` instead.
+/// `AlignMode::Suffix` is not supported, its results have to be mapped back by `edlib_align`.
+pub fn edlib_align_lazy(
+    query: &[u8],
+    target: &[u8],
+    aln_param: &EdlibAlignParam,
+) -> Result<LazyAlignResult, String> {
+    if aln_param.mode() == AlignMode::Suffix {
+        return Err("lazy results do not support AlignMode::Suffix".to_string());
+    }
+    let raw = align_raw(query, target, aln_param)?;
+    let within_error_rate = aln_param.within_error_rate(raw.editDistance, query.len());
+    Ok(LazyAlignResult {
+        raw,
+        within_error_rate,
+        collapse_locations: aln_param.collapse_locations(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edlib_align, param::AlignTask};
+
+    #[test]
+    fn test_edlib_align_lazy() {
+        let mut param = EdlibAlignParam::new(-1, AlignMode::Infix, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Extended);
+        let lazy_res = edlib_align_lazy(b"elephant", b"telephone", &param).unwrap();
+        let aln_res = edlib_align(b"elephant", b"telephone", &param).unwrap();
+
+        assert_eq!(lazy_res.edit_distance(), aln_res.edit_distance);
+        assert_eq!(lazy_res.locations().collect::<Vec<_>>(), aln_res.locations);
+        assert_eq!(lazy_res.cigar(CigarFmt::Extended), aln_res.cigar);
+        assert_eq!(lazy_res.to_result(CigarFmt::Extended), aln_res);
+        assert_eq!(lazy_res.path().unwrap().len(), 8);
+
+        param.set_task(AlignTask::Distance);
+        let lazy_res = edlib_align_lazy(b"elephant", b"telephone", &param).unwrap();
+        assert!(lazy_res.start_locations().is_none());
+        assert!(lazy_res.path().is_none());
+        assert_eq!(lazy_res.cigar(CigarFmt::Extended), None);
+        assert!(!lazy_res.end_locations().is_empty());
+    }
+
+    #[test]
+    fn test_edlib_align_lazy_collapse_locations() {
+        let mut param = EdlibAlignParam::new(-1, AlignMode::Infix, AlignTask::Locations);
+        param.set_collapse_locations(true);
+        let target = b"TTACGTTTTTACGTTT";
+        let lazy_res = edlib_align_lazy(b"ACGTA", target, &param).unwrap();
+        let aln_res = edlib_align(b"ACGTA", target, &param).unwrap();
+
+        assert_eq!(aln_res.locations.len(), 2);
+        assert_eq!(lazy_res.locations().collect::<Vec<_>>(), aln_res.locations);
+        assert_eq!(lazy_res.to_result(CigarFmt::NoCigar), aln_res);
+        assert!(lazy_res.end_locations().len() > 2);
+    }
+}
@@ -1,11 +1,31 @@
-use std::{ffi::CStr, ops::Deref};
+use std::{
+    ffi::CStr,
+    ops::{Bound, Deref, RangeBounds},
+};
 
 use edlib_sys::{edlibAlignmentToCigar, EdlibAlignConfig, EDLIB_STATUS_OK};
 use param::EdlibAlignParam;
 
+pub mod chunked;
+pub mod cigar;
+pub mod circular;
+pub mod cluster;
+pub mod consensus;
+pub mod dp;
 pub mod edlib_sys;
 
+pub mod generic;
+pub mod lazy;
+pub mod matrix;
+pub mod myers;
+pub mod occurrences;
+pub mod overlap;
+pub mod packed;
 pub mod param;
+pub mod prefilter;
+pub mod rescore;
+pub mod search;
+pub mod text;
 pub mod utils;
 
 #[derive(Debug)]
@@ -29,6 +49,44 @@ impl AlignResultGuard {
 
         start_ends
     }
+
+    /// None if no alignment path was computed.
+    fn path(&self) -> Option<&[u8]> {
+        if self.0.alignment.is_null() || self.0.alignmentLength <= 0 {
+            None
+        } else {
+            unsafe {
+                Some(std::slice::from_raw_parts(
+                    self.0.alignment,
+                    self.0.alignmentLength as usize,
+                ))
+            }
+        }
+    }
+
+    /// None if no alignment path was computed.
+    fn cigar(&self, cigar_fmt: param::CigarFmt) -> Option<String> {
+        path_to_cigar(self.path()?, cigar_fmt)
+    }
+}
+
+fn path_to_cigar(path: &[u8], cigar_fmt: param::CigarFmt) -> Option<String> {
+    let cigar_fmt = match cigar_fmt {
+        param::CigarFmt::NoCigar => return None,
+        param::CigarFmt::Standard => edlib_sys::EdlibCigarFormat_EDLIB_CIGAR_STANDARD,
+        param::CigarFmt::Extended => edlib_sys::EdlibCigarFormat_EDLIB_CIGAR_EXTENDED,
+    };
+
+    unsafe {
+        let cigar_str_guard: AlignCigarGuard =
+            edlibAlignmentToCigar(path.as_ptr(), path.len() as i32, cigar_fmt).into();
+        Some(
+            CStr::from_ptr(*cigar_str_guard)
+                .to_str()
+                .unwrap()
+                .to_string(),
+        )
+    }
 }
 
 impl Deref for AlignResultGuard {
@@ -75,25 +133,72 @@ impl Drop for AlignResultGuard {
     }
 }
 
-#[derive(Debug)]
+// SAFETY: the guard exclusively owns the buffers edlib malloc'ed for this result, they are
+// only read after edlibAlign returns and freed once on drop, which may happen on any thread.
+unsafe impl Send for AlignResultGuard {}
+// SAFETY: shared access only reads the buffers (edlibAlignmentToCigar takes a const pointer).
+unsafe impl Sync for AlignResultGuard {}
+
+/// Concurrency contract: params and results can be shared and sent between threads,
+/// lazy results own their edlib buffers and can be sent to and read from other threads.
+const _: () = {
+    const fn assert_send_sync<T: Send + Sync>() {}
+    assert_send_sync::<EdlibAlignParam>();
+    assert_send_sync::<EdlibAlignResult>();
+    assert_send_sync::<lazy::LazyAlignResult>();
+    assert_send_sync::<search::SeqIndex>();
+};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EdlibAlignResult {
     pub edit_distance: i32,
     pub alphabet_length: i32,
     pub locations: Vec<(usize, usize)>,
     pub cigar: Option<String>,
+    /// Whether the alignment satisfies the max error rate of the param, None if none was set.
+    #[cfg_attr(feature = "serde", serde(default))]
+    pub within_error_rate: Option<bool>,
 }
 
-pub fn edlib_align(
-    query: &[u8],
-    target: &[u8],
-    aln_param: &EdlibAlignParam,
-) -> Result<EdlibAlignResult, String> {
-    let config = EdlibAlignConfig {
-        k: aln_param.k(),
+impl EdlibAlignResult {
+    /// Merge each run of locations with adjacent end positions (as edlib reports around one
+    /// infix hit) into the first location of the run, so there is one location per distinct hit.
+    /// Suffix alignments all end at the last target base, so there runs of adjacent start
+    /// positions are merged instead.
+    /// The first location, which the cigar belongs to, is always kept.
+    pub fn collapse_locations(&mut self) {
+        self.locations.retain(first_of_adjacent_run());
+    }
+}
+
+/// Predicate over locations in edlib's order keeping only the first of each run of adjacent
+/// ends, or of adjacent starts for locations sharing their end.
+/// The end -1 edlib reports when deleting the whole query is optimal starts a run of its own.
+fn first_of_adjacent_run() -> impl FnMut(&(usize, usize)) -> bool {
+    let mut prev: Option<(usize, usize)> = None;
+    move |&(start, end)| {
+        let adjacent = prev.is_some_and(|(prev_start, prev_end)| {
+            if end == prev_end {
+                start.abs_diff(prev_start) <= 1
+            } else {
+                end.abs_diff(prev_end) <= 1
+            }
+        });
+        prev = Some((start, end));
+        !adjacent
+    }
+}
+
+fn align_config(aln_param: &EdlibAlignParam, query_len: usize) -> EdlibAlignConfig {
+    EdlibAlignConfig {
+        k: aln_param.effective_k(query_len),
         mode: match aln_param.mode() {
             param::AlignMode::Global => edlib_sys::EdlibAlignMode_EDLIB_MODE_NW,
             param::AlignMode::Prefix => edlib_sys::EdlibAlignMode_EDLIB_MODE_SHW,
             param::AlignMode::Infix => edlib_sys::EdlibAlignMode_EDLIB_MODE_HW,
+            // edlib_align reverses query and target for suffix alignments
+            param::AlignMode::Suffix => edlib_sys::EdlibAlignMode_EDLIB_MODE_SHW,
         },
         task: match aln_param.task() {
             param::AlignTask::Distance => edlib_sys::EdlibAlignTask_EDLIB_TASK_DISTANCE,
@@ -106,63 +211,147 @@ pub fn edlib_align(
             std::ptr::null()
         },
         additionalEqualitiesLength: aln_param.additional_eq_pairs().len() as i32,
-    };
+    }
+}
 
+fn align_raw(
+    query: &[u8],
+    target: &[u8],
+    aln_param: &EdlibAlignParam,
+) -> Result<AlignResultGuard, String> {
     let edlib_raw_res: AlignResultGuard = unsafe {
         edlib_sys::edlibAlign(
             query.as_ptr() as *const i8,
             query.len() as i32,
             target.as_ptr() as *const i8,
             target.len() as i32,
-            config,
+            align_config(aln_param, query.len()),
         )
         .into()
     };
 
-    // println!("{:?}", edlib_raw_res);
-
-    let align_cigar_str = if aln_param.task() == param::AlignTask::Path
-        && aln_param.cigar_fmt() != param::CigarFmt::NoCigar
-        && edlib_raw_res.status == EDLIB_STATUS_OK as i32
-    {
-        let cigar_fmt = match aln_param.cigar_fmt() {
-            param::CigarFmt::Standard => edlib_sys::EdlibCigarFormat_EDLIB_CIGAR_STANDARD,
-            param::CigarFmt::Extended => edlib_sys::EdlibCigarFormat_EDLIB_CIGAR_EXTENDED,
-            _ => panic!("Invalid cigar format"),
-        };
-
-        unsafe {
-            let cigar_str_guard: AlignCigarGuard = edlibAlignmentToCigar(
-                edlib_raw_res.alignment,
-                edlib_raw_res.alignmentLength,
-                cigar_fmt,
-            )
-            .into();
-            Some(
-                CStr::from_ptr(*cigar_str_guard)
-                    .to_str()
-                    .unwrap()
-                    .to_string(),
-            )
-        }
-    } else {
-        None
-    };
-
     if edlib_raw_res.status != EDLIB_STATUS_OK as i32 {
         Err(format!(
             "Edlib alignment failed with status: {}",
             edlib_raw_res.status
         ))
     } else {
-        // edlib_raw_res.target_start_ends()
-        Ok(EdlibAlignResult {
-            edit_distance: edlib_raw_res.editDistance,
-            alphabet_length: edlib_raw_res.alphabetLength,
-            locations: edlib_raw_res.target_start_ends(),
-            cigar: align_cigar_str,
+        Ok(edlib_raw_res)
+    }
+}
+
+/// Align query against target.
+/// k is capped by the max error rate of `aln_param`, see `EdlibAlignParam::effective_k`.
+/// edlib keeps no global state, so this can be called from many threads at once,
+/// sharing the same `EdlibAlignParam`.
+pub fn edlib_align(
+    query: &[u8],
+    target: &[u8],
+    aln_param: &EdlibAlignParam,
+) -> Result<EdlibAlignResult, String> {
+    if aln_param.mode() == param::AlignMode::Suffix {
+        return align_suffix(query, target, aln_param);
+    }
+    let edlib_raw_res = align_raw(query, target, aln_param)?;
+
+    let align_cigar_str = if aln_param.task() == param::AlignTask::Path {
+        edlib_raw_res.cigar(aln_param.cigar_fmt())
+    } else {
+        None
+    };
+
+    let mut aln_res = EdlibAlignResult {
+        edit_distance: edlib_raw_res.editDistance,
+        alphabet_length: edlib_raw_res.alphabetLength,
+        locations: edlib_raw_res.target_start_ends(),
+        cigar: align_cigar_str,
+        within_error_rate: aln_param.within_error_rate(edlib_raw_res.editDistance, query.len()),
+    };
+    if aln_param.collapse_locations() {
+        aln_res.collapse_locations();
+    }
+    Ok(aln_res)
+}
+
+/// Align query against `target[range]` only, e.g. the first or last N bases of a read.
+/// The range is checked against the target length, and locations are returned in
+/// coordinates of the whole target, so an end -1 in the window is reported as the base before it.
+pub fn edlib_align_in_window(
+    query: &[u8],
+    target: &[u8],
+    range: impl RangeBounds<usize>,
+    aln_param: &EdlibAlignParam,
+) -> Result<EdlibAlignResult, String> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => Some(start),
+        Bound::Excluded(&start) => start.checked_add(1),
+        Bound::Unbounded => Some(0),
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => end.checked_add(1),
+        Bound::Excluded(&end) => Some(end),
+        Bound::Unbounded => Some(target.len()),
+    };
+    let (start, end) = match (start, end) {
+        (Some(start), Some(end)) if start <= end && end <= target.len() => (start, end),
+        _ => {
+            return Err(format!(
+                "window from {:?} to {:?} out of bounds for target of length {}",
+                range.start_bound(),
+                range.end_bound(),
+                target.len()
+            ))
+        }
+    };
+
+    let mut aln_res = edlib_align(query, &target[start..end], aln_param)?;
+    for location in aln_res.locations.iter_mut() {
+        location.0 += start;
+        // the end -1 (whole query deleted) becomes the base before the window
+        location.1 = location.1.wrapping_add(start);
+    }
+    Ok(aln_res)
+}
+
+/// edlib has no suffix mode, so align the reversed sequences in prefix mode and map the
+/// locations and path back. Start locations are known even for `AlignTask::Distance`.
+fn align_suffix(
+    query: &[u8],
+    target: &[u8],
+    aln_param: &EdlibAlignParam,
+) -> Result<EdlibAlignResult, String> {
+    let rev_query: Vec<u8> = query.iter().rev().copied().collect();
+    let rev_target: Vec<u8> = target.iter().rev().copied().collect();
+    let edlib_raw_res = align_raw(&rev_query, &rev_target, aln_param)?;
+
+    // kept in edlib's order, the path belongs to the first location
+    let locations: Vec<(usize, usize)> = edlib_raw_res
+        .target_start_ends()
+        .into_iter()
+        .filter(|&(_, rev_end)| rev_end < target.len())
+        .map(|(_, rev_end)| (target.len() - 1 - rev_end, target.len() - 1))
+        .collect();
+
+    let align_cigar_str = if aln_param.task() == param::AlignTask::Path {
+        edlib_raw_res.path().and_then(|rev_path| {
+            let path: Vec<u8> = rev_path.iter().rev().copied().collect();
+            path_to_cigar(&path, aln_param.cigar_fmt())
         })
+    } else {
+        None
+    };
+
+    let mut aln_res = EdlibAlignResult {
+        edit_distance: edlib_raw_res.editDistance,
+        alphabet_length: edlib_raw_res.alphabetLength,
+        locations,
+        cigar: align_cigar_str,
+        within_error_rate: aln_param.within_error_rate(edlib_raw_res.editDistance, query.len()),
+    };
+    if aln_param.collapse_locations() {
+        aln_res.collapse_locations();
     }
+    Ok(aln_res)
 }
 
 #[cfg(test)]
@@ -198,4 +387,195 @@ mod tests {
         let aln_res = edlib_align(query, target, &param);
         println!("{:?}", aln_res);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_serde_round_trip() {
+        let mut param = EdlibAlignParam::new(3, param::AlignMode::Infix, param::AlignTask::Path);
+        param.set_cigar_fmt(param::CigarFmt::Extended);
+        param.add_eq_pair((b'N', b'A'));
+        let json = serde_json::to_string(&param).unwrap();
+        assert!(json.contains("\"additional_eq_pairs\":[[78,65]]"));
+        let param2: EdlibAlignParam = serde_json::from_str(&json).unwrap();
+        assert_eq!(param2, param);
+
+        let aln_res = edlib_align(b"ACGN", b"TTACGAT", &param).unwrap();
+        let json = serde_json::to_string(&aln_res).unwrap();
+        let aln_res2: EdlibAlignResult = serde_json::from_str(&json).unwrap();
+        assert_eq!(aln_res2, aln_res);
+
+        param.set_max_error_rate(0.25);
+        let json = serde_json::to_string(&param).unwrap();
+        assert_eq!(
+            serde_json::from_str::<EdlibAlignParam>(&json).unwrap(),
+            param
+        );
+        let json = json.replace("0.25", "-0.5");
+        assert!(serde_json::from_str::<EdlibAlignParam>(&json).is_err());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore = "edlib is foreign code")]
+    fn test_edlib_align_many_threads() {
+        let query = b"ACGTTGCAAGCTTACGGATC";
+        let targets: Vec<Vec<u8>> = (0..64)
+            .map(|i| {
+                let mut target = b"TTTTACGTTGCAAGCTTACGGATCTTTT".to_vec();
+                target[4 + i % 20] = b'N';
+                target
+            })
+            .collect();
+        let mut param = EdlibAlignParam::new(-1, param::AlignMode::Infix, param::AlignTask::Path);
+        param.set_cigar_fmt(param::CigarFmt::Extended);
+        let expected: Vec<EdlibAlignResult> = targets
+            .iter()
+            .map(|target| edlib_align(query, target, &param).unwrap())
+            .collect();
+
+        let lazy_results = std::thread::scope(|s| {
+            let handles: Vec<_> = (0..8)
+                .map(|_| {
+                    s.spawn(|| {
+                        for _ in 0..50 {
+                            for (target, expected) in targets.iter().zip(expected.iter()) {
+                                assert_eq!(&edlib_align(query, target, &param).unwrap(), expected);
+                            }
+                        }
+                        lazy::edlib_align_lazy(query, &targets[0], &param).unwrap()
+                    })
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().unwrap())
+                .collect::<Vec<_>>()
+        });
+
+        // results created on worker threads are read and dropped here
+        for lazy_res in lazy_results {
+            assert_eq!(lazy_res.to_result(param::CigarFmt::Extended), expected[0]);
+        }
+    }
+
+    #[test]
+    fn test_edlib_align_suffix() {
+        let mut param = EdlibAlignParam::new(-1, param::AlignMode::Suffix, param::AlignTask::Path);
+        param.set_cigar_fmt(param::CigarFmt::Extended);
+
+        // adapter anchored at the read end, with one base deleted in the read
+        let aln_res = edlib_align(b"GATCGGAAG", b"TTGATCTTGATCGAAG", &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 1);
+        assert_eq!(aln_res.locations, vec![(8, 15)]);
+        assert_eq!(aln_res.cigar.as_deref(), Some("5=1I3="));
+
+        param.set_task(param::AlignTask::Distance);
+        let aln_res = edlib_align(b"GATCGGAAG", b"TTGATCTTGATCGAAG", &param).unwrap();
+        assert_eq!(aln_res.locations, vec![(8, 15)]);
+
+        // the end of the target is not free
+        let aln_res = edlib_align(b"GATC", b"GATCTT", &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 2);
+    }
+
+    #[test]
+    fn test_collapse_locations() {
+        let mut param =
+            EdlibAlignParam::new(-1, param::AlignMode::Infix, param::AlignTask::Locations);
+        // the last base of each copy is missing, so every copy ends on two adjacent positions
+        let target = b"TTACGTTTTTACGTTT";
+        let aln_res = edlib_align(b"ACGTA", target, &param).unwrap();
+        assert!(aln_res.locations.len() > 2);
+
+        param.set_collapse_locations(true);
+        let collapsed = edlib_align(b"ACGTA", target, &param).unwrap();
+        assert_eq!(collapsed.edit_distance, aln_res.edit_distance);
+        assert_eq!(collapsed.locations.len(), 2);
+        assert_eq!(collapsed.locations[0], aln_res.locations[0]);
+    }
+
+    #[test]
+    fn test_collapse_locations_suffix() {
+        let mut param =
+            EdlibAlignParam::new(2, param::AlignMode::Suffix, param::AlignTask::Locations);
+        // the query ends the target within two edits starting at 0, 1 and 3
+        let aln_res = edlib_align(b"GATC", b"AATGC", &param).unwrap();
+        assert_eq!(aln_res.locations, vec![(3, 4), (1, 4), (0, 4)]);
+
+        param.set_collapse_locations(true);
+        let collapsed = edlib_align(b"GATC", b"AATGC", &param).unwrap();
+        assert_eq!(collapsed.locations, vec![(3, 4), (1, 4)]);
+
+        // the end -1 is not merged with the end 0
+        let mut aln_res = EdlibAlignResult {
+            edit_distance: 3,
+            alphabet_length: 2,
+            locations: vec![(0, usize::MAX), (0, 0), (0, 1)],
+            cigar: None,
+            within_error_rate: None,
+        };
+        aln_res.collapse_locations();
+        assert_eq!(aln_res.locations, vec![(0, usize::MAX), (0, 0)]);
+    }
+
+    #[test]
+    fn test_edlib_align_in_window() {
+        let mut param = EdlibAlignParam::new(-1, param::AlignMode::Infix, param::AlignTask::Path);
+        param.set_cigar_fmt(param::CigarFmt::Extended);
+        let read = b"GATCGGTTTTTTTTTTTTGATCGG";
+
+        let aln_res = edlib_align_in_window(b"GATCGG", read, 12.., &param).unwrap();
+        assert_eq!(aln_res.locations, vec![(18, 23)]);
+        assert_eq!(aln_res.cigar.as_deref(), Some("6="));
+
+        let aln_res = edlib_align_in_window(b"GATCGG", read, ..6, &param).unwrap();
+        assert_eq!(aln_res.locations, vec![(0, 5)]);
+        let aln_res = edlib_align_in_window(b"GATCGG", read, 2..=9, &param).unwrap();
+        assert!(aln_res
+            .locations
+            .iter()
+            .all(|&(start, end)| start >= 2 && end <= 9));
+
+        // deleting the whole query is as good as any hit among the Ts
+        let aln_res = edlib_align_in_window(b"GGGG", read, 8..12, &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 4);
+        assert_eq!(aln_res.locations[0], (8, 7));
+        assert!(aln_res.locations[1..]
+            .iter()
+            .all(|&(start, end)| start >= 8 && end < 12));
+
+        assert!(edlib_align_in_window(b"GATCGG", read, 20..30, &param).is_err());
+        assert!(edlib_align_in_window(b"GATCGG", read, ..=24, &param).is_err());
+        assert!(edlib_align_in_window(b"GATCGG", read, ..=usize::MAX, &param).is_err());
+        assert!(edlib_align_in_window(
+            b"GATCGG",
+            read,
+            (Bound::Excluded(usize::MAX), Bound::Unbounded),
+            &param
+        )
+        .is_err());
+        assert!(edlib_align_in_window(b"GATCGG", read, ..=23, &param).is_ok());
+    }
+
+    #[test]
+    fn test_max_error_rate() {
+        let mut param = EdlibAlignParam::new(-1, param::AlignMode::Infix, param::AlignTask::Path);
+        let aln_res = edlib_align(b"GATCGGAAG", b"TTGATCTTGATCGAAGTT", &param).unwrap();
+        assert_eq!(aln_res.within_error_rate, None);
+
+        // 1 edit in 9 bases
+        param.set_max_error_rate(0.12);
+        let aln_res = edlib_align(b"GATCGGAAG", b"TTGATCTTGATCGAAGTT", &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 1);
+        assert_eq!(aln_res.within_error_rate, Some(true));
+
+        param.set_max_error_rate(0.1);
+        let aln_res = edlib_align(b"GATCGGAAG", b"TTGATCTTGATCGAAGTT", &param).unwrap();
+        assert_eq!(aln_res.edit_distance, -1);
+        assert_eq!(aln_res.within_error_rate, Some(false));
+
+        // the same rate allows 2 edits for a query twice as long
+        let aln_res = edlib_align(b"GATCGGAAGAGCACAC", b"TTGATCGGTAGAGCACACTT", &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 1);
+        assert_eq!(aln_res.within_error_rate, Some(true));
+    }
 }
@@ -0,0 +1,233 @@
+use std::{borrow::Cow, io::Write};
+
+use crate::{
+    edlib_align,
+    param::{AlignMode, AlignTask, EdlibAlignParam},
+    utils::parallel_map,
+};
+
+/// Symmetric matrix of pairwise edit distances, stored condensed (upper triangle, row-major, no diagonal).
+/// A distance of -1 means the pair is further apart than the k used to compute the matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DistanceMatrix {
+    size: usize,
+    condensed: Vec<i32>,
+}
+
+impl DistanceMatrix {
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    /// Condensed upper triangle, in the order (0, 1), (0, 2), .., (1, 2), ..
+    pub fn condensed(&self) -> &[i32] {
+        &self.condensed
+    }
+
+    fn condensed_idx(&self, i: usize, j: usize) -> usize {
+        let (i, j) = if i < j { (i, j) } else { (j, i) };
+        i * (2 * self.size - i - 1) / 2 + (j - i - 1)
+    }
+
+    pub fn get(&self, i: usize, j: usize) -> i32 {
+        assert!(i < self.size && j < self.size);
+        if i == j {
+            0
+        } else {
+            self.condensed[self.condensed_idx(i, j)]
+        }
+    }
+
+    /// Write in square PHYLIP distance format. Names are truncated/padded to 10 chars.
+    /// Fails if truncated names are not unique, or if a pair is over k (stored as -1), since
+    /// PHYLIP has no missing distances; compute the matrix with a negative k for export.
+    pub fn write_phylip<W: Write>(&self, names: &[&str], writer: &mut W) -> std::io::Result<()> {
+        self.check_names(names)?;
+        if self.condensed.contains(&-1) {
+            return Err(invalid_input(
+                "PHYLIP can not store pairs over k, compute the matrix with a negative k"
+                    .to_string(),
+            ));
+        }
+        let names: Vec<String> = names
+            .iter()
+            .map(|name| name.chars().take(10).collect())
+            .collect();
+        for (i, name) in names.iter().enumerate() {
+            if names[..i].contains(name) {
+                return Err(invalid_input(format!(
+                    "duplicate name {:?} after truncating to 10 chars",
+                    name
+                )));
+            }
+        }
+
+        writeln!(writer, "{}", self.size)?;
+        for (i, name) in names.iter().enumerate() {
+            write!(writer, "{:<10}", name)?;
+            for j in 0..self.size {
+                write!(writer, " {}", self.get(i, j))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    /// Write as CSV with a header row and a leading name column, pairs over k are written as -1.
+    /// Names are quoted as needed (RFC 4180).
+    pub fn write_csv<W: Write>(&self, names: &[&str], writer: &mut W) -> std::io::Result<()> {
+        self.check_names(names)?;
+        let names: Vec<Cow<str>> = names.iter().map(|name| csv_field(name)).collect();
+        writeln!(writer, ",{}", names.join(","))?;
+        for (i, name) in names.iter().enumerate() {
+            write!(writer, "{}", name)?;
+            for j in 0..self.size {
+                write!(writer, ",{}", self.get(i, j))?;
+            }
+            writeln!(writer)?;
+        }
+        Ok(())
+    }
+
+    fn check_names(&self, names: &[&str]) -> std::io::Result<()> {
+        if names.len() != self.size {
+            return Err(invalid_input(format!(
+                "expected {} names, got {}",
+                self.size,
+                names.len()
+            )));
+        }
+        Ok(())
+    }
+}
+
+fn invalid_input(msg: String) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidInput, msg)
+}
+
+/// Quote a CSV field if it contains a separator, quote or line break.
+fn csv_field(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// Pairwise edit distances of seqs. Only global alignment is symmetric, so `aln_param` must use
+/// `AlignMode::Global`; its k caps the distances (pairs over k are stored as -1). A max error
+/// rate is relative to the shorter sequence of each pair, so the matrix does not depend on the
+/// order of seqs. Each pair is aligned once.
+pub fn distance_matrix(
+    seqs: &[&[u8]],
+    aln_param: &EdlibAlignParam,
+) -> Result<DistanceMatrix, String> {
+    distance_matrix_parallel(seqs, aln_param, 1)
+}
+
+/// Same as `distance_matrix`, with the rows spread over `threads` threads.
+pub fn distance_matrix_parallel(
+    seqs: &[&[u8]],
+    aln_param: &EdlibAlignParam,
+    threads: usize,
+) -> Result<DistanceMatrix, String> {
+    if aln_param.mode() != AlignMode::Global {
+        return Err(format!(
+            "distance matrix requires AlignMode::Global, got {:?}",
+            aln_param.mode()
+        ));
+    }
+    let mut aln_param = aln_param.clone();
+    aln_param.set_task(AlignTask::Distance);
+    let aln_param = &aln_param;
+
+    let size = seqs.len();
+    // rows are dealt round robin, row i holds size - i - 1 pairs so this roughly balances the work
+    let rows = parallel_map(size, threads, |i| {
+        ((i + 1)..size)
+            .map(|j| {
+                // the query length sets the effective k
+                let (query, target) = if seqs[i].len() <= seqs[j].len() {
+                    (seqs[i], seqs[j])
+                } else {
+                    (seqs[j], seqs[i])
+                };
+                Ok(edlib_align(query, target, aln_param)?.edit_distance)
+            })
+            .collect::<Result<Vec<i32>, String>>()
+    })?;
+
+    Ok(DistanceMatrix {
+        size,
+        condensed: rows.concat(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance_matrix() {
+        let seqs: [&[u8]; 4] = [b"ACGTACGT", b"ACGTACGA", b"ACGAACGA", b"TTTT"];
+        let mut param = EdlibAlignParam::default();
+        param.set_k(3);
+
+        let matrix = distance_matrix(&seqs, &param).unwrap();
+        assert_eq!(matrix.condensed(), &[1, 2, -1, 1, -1, -1]);
+        assert_eq!(matrix.get(2, 0), 2);
+        assert_eq!(matrix.get(3, 3), 0);
+        assert_eq!(distance_matrix_parallel(&seqs, &param, 3).unwrap(), matrix);
+
+        let mut csv = Vec::new();
+        matrix.write_csv(&["a", "b", "c", "d"], &mut csv).unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            ",a,b,c,d\na,0,1,2,-1\nb,1,0,1,-1\nc,2,1,0,-1\nd,-1,-1,-1,0\n"
+        );
+        assert!(matrix
+            .write_phylip(&["a", "b", "c", "d"], &mut Vec::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_distance_matrix_max_error_rate() {
+        let mut param = EdlibAlignParam::default();
+        param.set_max_error_rate(0.25);
+        // three edits: 2 allowed for the 10 bases of a, 3 for the 12 bases of b
+        let a: &[u8] = b"ACGTACGTAC";
+        let b: &[u8] = b"ACGTTCGTACGG";
+        assert_eq!(distance_matrix(&[a, b], &param).unwrap().condensed(), &[-1]);
+        assert_eq!(distance_matrix(&[b, a], &param).unwrap().condensed(), &[-1]);
+
+        param.set_max_error_rate(0.3);
+        assert_eq!(distance_matrix(&[b, a], &param).unwrap().condensed(), &[3]);
+    }
+
+    #[test]
+    fn test_distance_matrix_export() {
+        let seqs: [&[u8]; 3] = [b"ACGT", b"ACGA", b"TTTT"];
+        let matrix = distance_matrix(&seqs, &EdlibAlignParam::default()).unwrap();
+
+        let mut csv = Vec::new();
+        matrix
+            .write_csv(&["x,1", "say \"hi\"", "z"], &mut csv)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(csv).unwrap(),
+            ",\"x,1\",\"say \"\"hi\"\"\",z\n\"x,1\",0,1,3\n\"say \"\"hi\"\"\",1,0,4\nz,3,4,0\n"
+        );
+
+        let mut phylip = Vec::new();
+        matrix
+            .write_phylip(&["seq_a", "seq_b", "seq_c"], &mut phylip)
+            .unwrap();
+        assert_eq!(
+            String::from_utf8(phylip).unwrap(),
+            "3\nseq_a      0 1 3\nseq_b      1 0 4\nseq_c      3 4 0\n"
+        );
+        assert!(matrix
+            .write_phylip(&["sample_0001", "sample_0002", "c"], &mut Vec::new())
+            .is_err());
+    }
+}
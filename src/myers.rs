@@ -0,0 +1,236 @@
+use crate::param::{AlignMode, EdlibAlignParam};
+
+const WORD_SIZE: usize = 64;
+const HIGH_BIT: u64 = 1 << (WORD_SIZE - 1);
+
+/// Vertical deltas of one 64 row block of a DP column, as in edlib:
+/// bit i of `pv` (`mv`) is set if row i is one more (less) than the row above.
+#[derive(Debug, Clone, Copy)]
+struct Block {
+    pv: u64,
+    mv: u64,
+    /// DP value in the last row of the block
+    score: i64,
+}
+
+/// Advance a block by one column (edlib's `calculateBlock`).
+/// `eq` has a bit per row equal to the target base, `hin` is the horizontal delta entering the
+/// top row; returns the horizontal delta leaving the bottom row.
+fn advance_block(block: &mut Block, eq: u64, hin: i64) -> i64 {
+    let hin_neg = u64::from(hin < 0);
+    let xv = eq | block.mv;
+    let eq = eq | hin_neg;
+    let xh = ((eq & block.pv).wrapping_add(block.pv) ^ block.pv) | eq;
+
+    let mut ph = block.mv | !(xh | block.pv);
+    let mut mh = block.pv & xh;
+    let hout = if ph & HIGH_BIT != 0 {
+        1
+    } else if mh & HIGH_BIT != 0 {
+        -1
+    } else {
+        0
+    };
+
+    ph = (ph << 1) | u64::from(hin > 0);
+    mh = (mh << 1) | hin_neg;
+    block.pv = mh | !(xv | ph);
+    block.mv = ph & xv;
+    block.score += hout;
+    hout
+}
+
+/// The blocks of the current DP column, advanced one target base at a time.
+struct Columns {
+    peq: Vec<u64>,
+    num_blocks: usize,
+    blocks: Vec<Block>,
+    hin_top: i64,
+    /// rows of the last block below the query, their deltas are subtracted from its score
+    below_query: u64,
+    /// DP value in the top row of the current column
+    top: usize,
+}
+
+impl Columns {
+    /// First column of the DP matrix, query must not be empty.
+    fn new(query: &[u8], aln_param: &EdlibAlignParam) -> Self {
+        let equal = aln_param.equality_table();
+
+        // peq[c * num_blocks + b]: rows of block b equal to c, padding rows past the query match nothing
+        let num_blocks = query.len().div_ceil(WORD_SIZE);
+        let mut peq = vec![0u64; 256 * num_blocks];
+        for (i, &q) in query.iter().enumerate() {
+            for c in 0..256 {
+                if equal[q as usize][c] {
+                    peq[c * num_blocks + i / WORD_SIZE] |= 1 << (i % WORD_SIZE);
+                }
+            }
+        }
+
+        // first column: D[i][0] = i
+        let blocks = (0..num_blocks)
+            .map(|b| Block {
+                pv: u64::MAX,
+                mv: 0,
+                score: ((b + 1) * WORD_SIZE) as i64,
+            })
+            .collect();
+
+        let padding = (num_blocks * WORD_SIZE - query.len()) as u32;
+        let free_start = matches!(aln_param.mode(), AlignMode::Infix | AlignMode::Suffix);
+        Columns {
+            peq,
+            num_blocks,
+            blocks,
+            hin_top: if free_start { 0 } else { 1 },
+            below_query: u64::MAX
+                .checked_shl(WORD_SIZE as u32 - padding)
+                .unwrap_or(0),
+            top: 0,
+        }
+    }
+
+    fn advance(&mut self, t: u8) {
+        let peq_t = &self.peq[t as usize * self.num_blocks..(t as usize + 1) * self.num_blocks];
+        let mut hin = self.hin_top;
+        for (block, &eq) in self.blocks.iter_mut().zip(peq_t) {
+            hin = advance_block(block, eq, hin);
+        }
+        self.top += self.hin_top as usize;
+    }
+
+    /// DP value in the last query row.
+    fn last_row(&self) -> usize {
+        let last = self.blocks[self.num_blocks - 1];
+        let below = i64::from((last.pv & self.below_query).count_ones())
+            - i64::from((last.mv & self.below_query).count_ones());
+        (last.score - below) as usize
+    }
+
+    /// DP values in the query rows, without the top row.
+    fn rows(&self, query_len: usize) -> Vec<usize> {
+        let mut value = self.top as i64;
+        (0..query_len)
+            .map(|i| {
+                let block = self.blocks[i / WORD_SIZE];
+                let bit = 1 << (i % WORD_SIZE);
+                value += i64::from(block.pv & bit != 0) - i64::from(block.mv & bit != 0);
+                value as usize
+            })
+            .collect()
+    }
+}
+
+/// Minimal edit distance of the whole query ending at each target position, i.e. the last row
+/// of the DP matrix edlib works on, computed with Myers' bit-vector algorithm in pure Rust.
+///
+/// `profile[j]` aligns the query to a target substring ending at j. The start is free in
+/// Infix and Suffix mode and anchored at 0 in Global and Prefix mode, so the minimum of the
+/// profile (or its last value) is what `edlib_align` reports for the mode.
+/// Equality pairs are honored, k and task are ignored: the full row is always computed,
+/// in O(ceil(query len / 64) * target len) time.
+pub fn distance_profile(query: &[u8], target: &[u8], aln_param: &EdlibAlignParam) -> Vec<usize> {
+    if query.is_empty() {
+        let free_start = matches!(aln_param.mode(), AlignMode::Infix | AlignMode::Suffix);
+        return (1..=target.len())
+            .map(|j| if free_start { 0 } else { j })
+            .collect();
+    }
+
+    let mut columns = Columns::new(query, aln_param);
+    target
+        .iter()
+        .map(|&t| {
+            columns.advance(t);
+            columns.last_row()
+        })
+        .collect()
+}
+
+/// Minimal edit distance of each query prefix ending at the last target base, i.e. the last
+/// column of the DP matrix of `distance_profile`.
+///
+/// `column[i]` aligns `query[..=i]` to a target suffix in Infix and Suffix mode, and to the
+/// whole target in Global and Prefix mode. Takes the same time as `distance_profile`.
+pub fn distance_column(query: &[u8], target: &[u8], aln_param: &EdlibAlignParam) -> Vec<usize> {
+    if query.is_empty() {
+        return Vec::new();
+    }
+
+    let mut columns = Columns::new(query, aln_param);
+    target.iter().for_each(|&t| columns.advance(t));
+    columns.rows(query.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{edlib_align, param::AlignTask};
+
+    #[test]
+    fn test_distance_profile_matches_edlib() {
+        // over two blocks, with a copy of most of the query in the middle of the target
+        let query: Vec<u8> = b"ACGTTGCAAGCTTACGGATCN".repeat(5);
+        let mut target: Vec<u8> = b"TTGCA".repeat(10);
+        target.extend_from_slice(&query[3..90]);
+        target.extend_from_slice(b"GGGATTACA");
+
+        for (mode, prefix_mode) in [
+            (AlignMode::Infix, AlignMode::Suffix),
+            (AlignMode::Prefix, AlignMode::Global),
+        ] {
+            let mut param = EdlibAlignParam::new(-1, mode, AlignTask::Distance);
+            param.add_eq_pair((b'N', b'G'));
+            let profile = distance_profile(&query, &target, &param);
+            assert_eq!(profile.len(), target.len());
+
+            let mut prefix_param = param.clone();
+            prefix_param.set_mode(prefix_mode);
+            for (j, &dist) in profile.iter().enumerate() {
+                let aln_res = edlib_align(&query, &target[..=j], &prefix_param).unwrap();
+                assert_eq!(dist, aln_res.edit_distance as usize, "end {}", j);
+            }
+
+            let aln_res = edlib_align(&query, &target, &param).unwrap();
+            assert_eq!(
+                *profile.iter().min().unwrap(),
+                aln_res.edit_distance as usize
+            );
+        }
+    }
+
+    #[test]
+    fn test_distance_column_matches_edlib() {
+        let query: Vec<u8> = b"GATTACAGGCCTTAGCN".repeat(6);
+        let target: Vec<u8> = [b"ACCATTGA".as_slice(), &query[..70]].concat();
+
+        for (mode, prefix_mode) in [
+            (AlignMode::Infix, AlignMode::Suffix),
+            (AlignMode::Prefix, AlignMode::Global),
+        ] {
+            let mut param = EdlibAlignParam::new(-1, mode, AlignTask::Distance);
+            param.add_eq_pair((b'N', b'T'));
+            let column = distance_column(&query, &target, &param);
+            assert_eq!(column.len(), query.len());
+
+            let mut prefix_param = param.clone();
+            prefix_param.set_mode(prefix_mode);
+            for (i, &dist) in column.iter().enumerate() {
+                let aln_res = edlib_align(&query[..=i], &target, &prefix_param).unwrap();
+                assert_eq!(dist, aln_res.edit_distance as usize, "row {}", i);
+            }
+        }
+
+        let param = EdlibAlignParam::new(-1, AlignMode::Global, AlignTask::Distance);
+        assert_eq!(distance_column(b"ACG", b"", &param), vec![1, 2, 3]);
+        assert!(distance_column(b"", b"ACG", &param).is_empty());
+    }
+
+    #[test]
+    fn test_distance_profile_empty_query() {
+        let param = EdlibAlignParam::new(-1, AlignMode::Global, AlignTask::Distance);
+        assert_eq!(distance_profile(b"", b"ACG", &param), vec![1, 2, 3]);
+        assert!(distance_profile(b"ACG", b"", &param).is_empty());
+    }
+}
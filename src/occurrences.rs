@@ -0,0 +1,203 @@
+use std::collections::VecDeque;
+
+use crate::{
+    edlib_align,
+    param::{AlignMode, AlignTask, CigarFmt, EdlibAlignParam},
+};
+
+/// One approximate occurrence of the query in the target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Occurrence {
+    /// (start, end) in target, inclusive like `EdlibAlignResult::locations`
+    pub start: usize,
+    pub end: usize,
+    pub edit_distance: i32,
+    /// path of the query against target[start..=end], in the cigar format of the param
+    pub cigar: Option<String>,
+}
+
+/// Iterator over every occurrence of a query within k edits, see `find_all_within`.
+///
+/// edlib only reports the optimal end locations, so occurrences are found round by round:
+/// the optimal locations of the current round are merged into distinct hits with
+/// `EdlibAlignResult::collapse_locations`, yielded, and masked out of the target with a byte that can not
+/// match the query, until nothing within k is left.
+/// Hits therefore come best first, and a hit overlapping an earlier one is only found if it is
+/// within k without the masked bases.
+pub struct ApproxOccurrences<'a> {
+    query: &'a [u8],
+    target: &'a [u8],
+    masked: Vec<u8>,
+    mask: u8,
+    aln_param: EdlibAlignParam,
+    cigar_fmt: CigarFmt,
+    pending: VecDeque<Occurrence>,
+    done: bool,
+}
+
+impl<'a> ApproxOccurrences<'a> {
+    /// Uses k, max error rate and equality pairs of `aln_param`; the resulting k must be
+    /// non-negative, mode and task are ignored.
+    pub fn new(
+        query: &'a [u8],
+        target: &'a [u8],
+        aln_param: &EdlibAlignParam,
+    ) -> Result<Self, String> {
+        if aln_param.effective_k(query.len()) < 0 {
+            return Err("finding all occurrences requires a non-negative k".to_string());
+        }
+
+        let mut used = [false; 256];
+        query.iter().for_each(|&c| used[c as usize] = true);
+        for pair in aln_param.additional_eq_pairs() {
+            used[pair.first as u8 as usize] = true;
+            used[pair.second as u8 as usize] = true;
+        }
+        let mask = (0..=255u8).find(|&c| !used[c as usize]).ok_or(
+            "query and equality pairs use all 256 byte values, none is left to mask hits with",
+        )?;
+
+        let mut locate_param = aln_param.clone();
+        locate_param.set_mode(AlignMode::Infix);
+        locate_param.set_task(AlignTask::Locations);
+        locate_param.set_collapse_locations(false);
+
+        Ok(ApproxOccurrences {
+            query,
+            target,
+            masked: target.to_vec(),
+            mask,
+            aln_param: locate_param,
+            cigar_fmt: aln_param.cigar_fmt(),
+            pending: VecDeque::new(),
+            done: false,
+        })
+    }
+
+    fn next_round(&mut self) -> Result<(), String> {
+        let mut aln_res = edlib_align(self.query, &self.masked, &self.aln_param)?;
+        // edlib reports end -1 when deleting the whole query is optimal, and once k reaches the
+        // query length masked bases are within k as well; neither is a new hit
+        let (masked, mask) = (&self.masked, self.mask);
+        aln_res.locations.retain(|&(start, end)| {
+            start <= end && end < masked.len() && masked[start..=end].iter().any(|&c| c != mask)
+        });
+        if aln_res.edit_distance < 0 || aln_res.locations.is_empty() {
+            self.done = true;
+            return Ok(());
+        }
+
+        // mask every optimal end, not only the representatives, so a hit is not found again
+        for &(start, end) in &aln_res.locations {
+            self.masked[start..=end].fill(self.mask);
+        }
+        let mut hits = aln_res;
+        hits.collapse_locations();
+        for &(start, end) in &hits.locations {
+            self.pending.push_back(self.occurrence(start, end)?);
+        }
+        Ok(())
+    }
+
+    /// Realign against the unmasked target to get the distance and path of the hit itself.
+    fn occurrence(&self, start: usize, end: usize) -> Result<Occurrence, String> {
+        let mut path_param = self.aln_param.clone();
+        path_param.set_mode(AlignMode::Global);
+        path_param.set_k(-1);
+        path_param.set_task(if self.cigar_fmt == CigarFmt::NoCigar {
+            AlignTask::Distance
+        } else {
+            AlignTask::Path
+        });
+        path_param.set_cigar_fmt(self.cigar_fmt);
+        let aln_res = edlib_align(self.query, &self.target[start..=end], &path_param)?;
+
+        Ok(Occurrence {
+            start,
+            end,
+            edit_distance: aln_res.edit_distance,
+            cigar: aln_res.cigar,
+        })
+    }
+}
+
+impl Iterator for ApproxOccurrences<'_> {
+    type Item = Result<Occurrence, String>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pending.is_empty() && !self.done {
+            if let Err(err) = self.next_round() {
+                self.done = true;
+                return Some(Err(err));
+            }
+        }
+        self.pending.pop_front().map(Ok)
+    }
+}
+
+/// Every occurrence of query in target with at most k edits, best first, with extended cigars.
+/// Fails if k does not fit edlib's i32, or if the query uses all 256 byte values (see
+/// `ApproxOccurrences::new`).
+pub fn find_all_within<'a>(
+    query: &'a [u8],
+    target: &'a [u8],
+    k: usize,
+) -> Result<ApproxOccurrences<'a>, String> {
+    let k = i32::try_from(k).map_err(|_| format!("k {} is too large for edlib", k))?;
+    let mut aln_param = EdlibAlignParam::new(k, AlignMode::Infix, AlignTask::Path);
+    aln_param.set_cigar_fmt(CigarFmt::Extended);
+    ApproxOccurrences::new(query, target, &aln_param)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_all_within() {
+        // three adapter copies: exact, one mismatch, one deletion
+        let adapter = b"GATCGGAAGAGC";
+        let read = b"TTGATCGGAAGAGCTTTTGATCGGTAGAGCTTTTGATCGAAGAGCTTTTGATCG";
+
+        let hits: Vec<Occurrence> = find_all_within(adapter, read, 1)
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap();
+        assert_eq!(hits.len(), 3);
+        assert_eq!(hits[0].start, 2);
+        assert_eq!(hits[0].end, 13);
+        assert_eq!(hits[0].cigar.as_deref(), Some("12="));
+
+        let mut found: Vec<(usize, usize, i32)> = hits
+            .iter()
+            .map(|hit| (hit.start, hit.end, hit.edit_distance))
+            .collect();
+        found.sort_unstable();
+        assert_eq!(found, vec![(2, 13, 0), (18, 29, 1), (34, 44, 1)]);
+
+        assert_eq!(find_all_within(adapter, read, 0).unwrap().count(), 1);
+        assert_eq!(
+            find_all_within(adapter, b"TTTTTTTTTTTT", 2)
+                .unwrap()
+                .count(),
+            0
+        );
+    }
+
+    #[test]
+    fn test_find_all_within_whole_query_deleted() {
+        // k reaches the query length, so edlib also reports the end -1
+        for hit in find_all_within(b"ACG", b"TTTTTT", 3).unwrap() {
+            let hit = hit.unwrap();
+            assert!(hit.start <= hit.end && hit.end < 6);
+            assert_eq!(hit.edit_distance, 3);
+        }
+    }
+
+    #[test]
+    fn test_find_all_within_invalid() {
+        let all_bytes: Vec<u8> = (0..=255).collect();
+        assert!(find_all_within(&all_bytes, b"ACGT", 1).is_err());
+        assert!(find_all_within(b"ACGT", b"ACGT", usize::MAX).is_err());
+    }
+}
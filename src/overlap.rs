@@ -0,0 +1,341 @@
+use crate::{
+    cigar::{parse_cigar, to_cigar, AlignOp},
+    edlib_align,
+    myers::distance_column,
+    param::{AlignMode, EdlibAlignParam},
+};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OverlapOrientation {
+    /// a suffix overlaps b prefix
+    AThenB,
+    /// b suffix overlaps a prefix
+    BThenA,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Overlap {
+    pub orientation: OverlapOrientation,
+    /// number of bases of a in the overlap
+    pub length: usize,
+    /// overlapping part of a, (start, end) inclusive like `EdlibAlignResult::locations`
+    pub a_range: (usize, usize),
+    /// overlapping part of b, (start, end) inclusive
+    pub b_range: (usize, usize),
+    pub edit_distance: i32,
+    /// cigar of the overlap with a as query and b as target, if `aln_param` asks for a path
+    pub cigar: Option<String>,
+}
+
+/// Find the longest dovetail overlap between a and b, in either orientation, with at most k edits.
+///
+/// The distances of all overlap lengths are computed at once per orientation, as the last DP
+/// column of the reversed upstream suffix against the reversed downstream prefix (see
+/// `myers::distance_column`), and only the longest length within k is aligned with
+/// `edlib_align` for its location and path.
+/// `aln_param` must have a non-negative k or a max error rate, which bounds the edits of each
+/// overlap length; its mode is ignored. Ties in length prefer the smaller distance.
+/// Overlaps of k bases or fewer are never reported, since any two sequences overlap that far within k.
+pub fn overlap_align(
+    a: &[u8],
+    b: &[u8],
+    min_overlap: usize,
+    aln_param: &EdlibAlignParam,
+) -> Result<Option<Overlap>, String> {
+    if aln_param.k() < 0 && aln_param.max_error_rate().is_none() {
+        return Err("overlap alignment requires a non-negative k or a max error rate".to_string());
+    }
+    let mut aln_param = aln_param.clone();
+    aln_param.set_mode(AlignMode::Prefix);
+
+    let a_then_b = longest_suffix_prefix_overlap(a, b, min_overlap, &aln_param)?;
+    let b_then_a =
+        longest_suffix_prefix_overlap(b, a, min_overlap, &aln_param)?.map(|overlap| Overlap {
+            orientation: OverlapOrientation::BThenA,
+            length: overlap.b_range.1 + 1 - overlap.b_range.0,
+            a_range: overlap.b_range,
+            b_range: overlap.a_range,
+            edit_distance: overlap.edit_distance,
+            cigar: overlap
+                .cigar
+                .map(|cigar| swap_query_target(&cigar, &aln_param)),
+        });
+
+    Ok(match (a_then_b, b_then_a) {
+        (Some(ab), Some(ba)) => {
+            if (ba.length, -ba.edit_distance) > (ab.length, -ab.edit_distance) {
+                Some(ba)
+            } else {
+                Some(ab)
+            }
+        }
+        (ab, ba) => ab.or(ba),
+    })
+}
+
+/// Longest overlap of an upstream suffix with a downstream prefix, reported as AThenB.
+fn longest_suffix_prefix_overlap(
+    upstream: &[u8],
+    downstream: &[u8],
+    min_overlap: usize,
+    aln_param: &EdlibAlignParam,
+) -> Result<Option<Overlap>, String> {
+    // effective k grows with the overlap length
+    let max_k = aln_param.effective_k(upstream.len()) as usize;
+    let max_len = upstream.len().min(downstream.len() + max_k);
+    let min_len = min_overlap.max(1);
+    if max_len < min_len {
+        return Ok(None);
+    }
+
+    // row len - 1 of the column: the upstream suffix of length len against the best downstream
+    // prefix, as the reversed suffix against a free start in the reversed prefix
+    let rev_suffix: Vec<u8> = upstream[upstream.len() - max_len..]
+        .iter()
+        .rev()
+        .copied()
+        .collect();
+    let rev_prefix: Vec<u8> = downstream[..downstream.len().min(max_len + max_k)]
+        .iter()
+        .rev()
+        .copied()
+        .collect();
+    let mut column_param = aln_param.clone();
+    column_param.set_mode(AlignMode::Infix);
+    let distances = distance_column(&rev_suffix, &rev_prefix, &column_param);
+
+    for len in (min_len..=max_len).rev() {
+        let k = aln_param.effective_k(len) as usize;
+        // at len <= k the whole query can be edited away, so every pair would pass
+        if len <= k || distances[len - 1] > k {
+            continue;
+        }
+        let query = &upstream[upstream.len() - len..];
+        // the aligned downstream prefix is at most k bases longer than the query
+        let target = &downstream[..downstream.len().min(len + k)];
+        let aln_res = edlib_align(query, target, aln_param)?;
+        if aln_res.edit_distance < 0 {
+            continue;
+        }
+        // edlib reports end -1 for an empty aligned prefix, like in align_suffix
+        let Some(&(_, end)) = aln_res
+            .locations
+            .iter()
+            .find(|&&(_, end)| end < target.len())
+        else {
+            continue;
+        };
+        return Ok(Some(Overlap {
+            orientation: OverlapOrientation::AThenB,
+            length: len,
+            a_range: (upstream.len() - len, upstream.len() - 1),
+            b_range: (0, end),
+            edit_distance: aln_res.edit_distance,
+            cigar: aln_res.cigar,
+        }));
+    }
+
+    Ok(None)
+}
+
+fn swap_query_target(cigar: &str, aln_param: &EdlibAlignParam) -> String {
+    let ops: Vec<AlignOp> = parse_cigar(cigar)
+        .expect("edlib produced an invalid cigar")
+        .into_iter()
+        .flat_map(|(op, len)| {
+            let op = match op {
+                AlignOp::Insertion => AlignOp::Deletion,
+                AlignOp::Deletion => AlignOp::Insertion,
+                op => op,
+            };
+            std::iter::repeat_n(op, len)
+        })
+        .collect();
+    to_cigar(&ops, aln_param.cigar_fmt()).unwrap_or_default()
+}
+
+/// Which of two sequences contains the other.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Container {
+    A,
+    B,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Containment {
+    pub container: Container,
+    /// locations of the contained sequence, in coordinates of the container
+    pub locations: Vec<(usize, usize)>,
+    pub edit_distance: i32,
+    /// cigar with the contained sequence as query, if `aln_param` asks for a path
+    pub cigar: Option<String>,
+}
+
+/// Check whether a is contained in b or b in a, within the k of `aln_param`, capped by its max
+/// error rate relative to the contained sequence (one of both must be set).
+///
+/// The shorter sequence is aligned in infix mode against the longer one; if the lengths differ
+/// by at most k the longer one may fit into the shorter as well, and the closer fit wins.
+/// The mode of `aln_param` is ignored.
+pub fn containment(
+    a: &[u8],
+    b: &[u8],
+    aln_param: &EdlibAlignParam,
+) -> Result<Option<Containment>, String> {
+    if aln_param.k() < 0 && aln_param.max_error_rate().is_none() {
+        return Err(
+            "containment detection requires a non-negative k or a max error rate".to_string(),
+        );
+    }
+    let mut aln_param = aln_param.clone();
+    aln_param.set_mode(AlignMode::Infix);
+
+    let (shorter, longer, container) = if a.len() <= b.len() {
+        (a, b, Container::B)
+    } else {
+        (b, a, Container::A)
+    };
+    let mut candidates = vec![(shorter, longer, container)];
+    if longer.len() - shorter.len() <= aln_param.effective_k(longer.len()) as usize {
+        let other = match container {
+            Container::A => Container::B,
+            Container::B => Container::A,
+        };
+        candidates.push((longer, shorter, other));
+    }
+
+    let mut best: Option<Containment> = None;
+    for (contained, container_seq, container) in candidates {
+        let aln_res = edlib_align(contained, container_seq, &aln_param)?;
+        if aln_res.edit_distance < 0
+            || best
+                .as_ref()
+                .is_some_and(|best| best.edit_distance <= aln_res.edit_distance)
+        {
+            continue;
+        }
+        best = Some(Containment {
+            container,
+            locations: aln_res.locations,
+            edit_distance: aln_res.edit_distance,
+            cigar: aln_res.cigar,
+        });
+    }
+
+    Ok(best)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{AlignTask, CigarFmt};
+
+    #[test]
+    fn test_overlap_align() {
+        let mut param = EdlibAlignParam::new(1, AlignMode::Global, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Extended);
+
+        // a ends with GATTACAGG, b starts with GATTCAGG (one base deleted)
+        let a = b"TTTTTTTTTTGATTACAGG";
+        let b = b"GATTCAGGCCCCCCCCCC";
+        let overlap = overlap_align(a, b, 5, &param).unwrap().unwrap();
+        assert_eq!(overlap.orientation, OverlapOrientation::AThenB);
+        assert_eq!(overlap.length, 9);
+        assert_eq!(overlap.a_range, (10, 18));
+        assert_eq!(overlap.b_range, (0, 7));
+        assert_eq!(overlap.edit_distance, 1);
+
+        let overlap = overlap_align(b, a, 5, &param).unwrap().unwrap();
+        assert_eq!(overlap.orientation, OverlapOrientation::BThenA);
+        assert_eq!(overlap.a_range, (0, 7));
+        assert_eq!(overlap.b_range, (10, 18));
+        assert_eq!(overlap.cigar.as_deref(), Some("4=1D4="));
+
+        assert_eq!(overlap_align(a, b"CCCCCCCCCC", 5, &param).unwrap(), None);
+    }
+
+    #[test]
+    fn test_overlap_align_long_reads() {
+        let mut state = 7u64;
+        let genome: Vec<u8> = (0..3000)
+            .map(|_| {
+                state = state.wrapping_mul(6364136223846793005).wrapping_add(1);
+                b"ACGT"[(state >> 62) as usize]
+            })
+            .collect();
+        // 1500 bases of overlap, with a mismatch and a deleted base in b
+        let a = genome[..2000].to_vec();
+        let mut b = genome[500..].to_vec();
+        b[100] = if b[100] == b'A' { b'C' } else { b'A' };
+        b.remove(1000);
+
+        let param = EdlibAlignParam::new(2, AlignMode::Global, AlignTask::Path);
+        let overlap = overlap_align(&a, &b, 100, &param).unwrap().unwrap();
+        assert_eq!(overlap.orientation, OverlapOrientation::AThenB);
+        assert_eq!(overlap.length, 1500);
+        assert_eq!(overlap.a_range, (500, 1999));
+        assert_eq!(overlap.b_range, (0, 1498));
+        assert_eq!(overlap.edit_distance, 2);
+    }
+
+    #[test]
+    fn test_overlap_align_min_overlap_within_k() {
+        let mut param = EdlibAlignParam::new(2, AlignMode::Global, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Extended);
+        // overlaps of up to k bases are edited away entirely and must not count
+        assert_eq!(
+            overlap_align(b"AAAAAAAAAA", b"CCCCCCCCCC", 1, &param).unwrap(),
+            None
+        );
+        assert_eq!(overlap_align(b"AAAAAAAAAA", b"", 0, &param).unwrap(), None);
+    }
+
+    #[test]
+    fn test_containment() {
+        let param = EdlibAlignParam::new(1, AlignMode::Global, AlignTask::Locations);
+        let long = b"TTTTGATTACAGGCCCC";
+        let short = b"GATTCAGG";
+
+        let contained = containment(long, short, &param).unwrap().unwrap();
+        assert_eq!(contained.container, Container::A);
+        assert_eq!(contained.locations, vec![(4, 12)]);
+        assert_eq!(contained.edit_distance, 1);
+
+        let contained = containment(short, long, &param).unwrap().unwrap();
+        assert_eq!(contained.container, Container::B);
+        assert_eq!(contained.locations, vec![(4, 12)]);
+
+        // equal up to one extra base: the longer one fits into the shorter one with a deletion
+        let contained = containment(b"ACGTACGT", b"ACGTTACGT", &param)
+            .unwrap()
+            .unwrap();
+        assert_eq!(contained.edit_distance, 1);
+
+        assert_eq!(containment(long, b"GGGGGGGG", &param).unwrap(), None);
+    }
+
+    #[test]
+    fn test_max_error_rate_only() {
+        let mut param = EdlibAlignParam::new(-1, AlignMode::Global, AlignTask::Locations);
+        param.set_max_error_rate(0.15);
+
+        // one edit in the 8 base short read is within 15%
+        let contained = containment(b"TTTTGATTACAGGCCCC", b"GATTCAGG", &param)
+            .unwrap()
+            .unwrap();
+        assert_eq!(contained.edit_distance, 1);
+
+        // the 9 base overlap allows one edit, shorter ones none
+        let overlap = overlap_align(b"TTTTTTTTTTGATTACAGG", b"GATTCAGGCCCCCCCCCC", 5, &param)
+            .unwrap()
+            .unwrap();
+        assert_eq!(overlap.length, 9);
+        assert_eq!(overlap.edit_distance, 1);
+
+        param.set_max_error_rate(0.1);
+        assert_eq!(
+            overlap_align(b"TTTTTTTTTTGATTACAGG", b"GATTCAGGCCCCCCCCCC", 5, &param).unwrap(),
+            None
+        );
+    }
+}
@@ -0,0 +1,246 @@
+use std::ops::Range;
+
+use crate::utils::SEQ_NT4_TABLE;
+
+const BASES_PER_WORD: usize = 32;
+const NT4_TO_BASE: [u8; 4] = *b"ACGT";
+
+/// DNA sequence stored at 2 bits per base.
+/// Bases other than ACGT (acgt) are remembered in a sorted side list and decoded as N,
+/// lowercase bases are decoded as uppercase.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PackedDna {
+    words: Vec<u64>,
+    len: usize,
+    n_positions: Vec<usize>,
+}
+
+impl PackedDna {
+    pub fn new() -> Self {
+        PackedDna::default()
+    }
+
+    pub fn with_capacity(len: usize) -> Self {
+        PackedDna {
+            words: Vec::with_capacity(len.div_ceil(BASES_PER_WORD)),
+            len: 0,
+            n_positions: Vec::new(),
+        }
+    }
+
+    pub fn push(&mut self, base: u8) {
+        let code = SEQ_NT4_TABLE[base as usize];
+        if code > 3 {
+            self.n_positions.push(self.len);
+            self.push_code(0);
+        } else {
+            self.push_code(code);
+        }
+    }
+
+    fn push_code(&mut self, code: u8) {
+        let offset = self.len % BASES_PER_WORD;
+        if offset == 0 {
+            self.words.push(0);
+        }
+        *self.words.last_mut().unwrap() |= (code as u64) << (2 * offset);
+        self.len += 1;
+    }
+
+    fn code(&self, pos: usize) -> u8 {
+        ((self.words[pos / BASES_PER_WORD] >> (2 * (pos % BASES_PER_WORD))) & 0b11) as u8
+    }
+
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn is_n(&self, pos: usize) -> bool {
+        self.n_positions.binary_search(&pos).is_ok()
+    }
+
+    /// Decoded base at pos, None if out of bounds.
+    pub fn get(&self, pos: usize) -> Option<u8> {
+        if pos >= self.len {
+            None
+        } else if self.is_n(pos) {
+            Some(b'N')
+        } else {
+            Some(NT4_TO_BASE[self.code(pos) as usize])
+        }
+    }
+
+    /// Copy of the bases in range. Panics if range is out of bounds, like slice indexing.
+    pub fn slice(&self, range: Range<usize>) -> PackedDna {
+        assert!(
+            range.start <= range.end && range.end <= self.len,
+            "range {:?} out of bounds for PackedDna of length {}",
+            range,
+            self.len
+        );
+
+        let mut sliced = PackedDna::with_capacity(range.len());
+        for pos in range.clone() {
+            sliced.push_code(self.code(pos));
+        }
+        let first_n = self.n_positions.partition_point(|&pos| pos < range.start);
+        let last_n = self.n_positions.partition_point(|&pos| pos < range.end);
+        sliced.n_positions = self.n_positions[first_n..last_n]
+            .iter()
+            .map(|pos| pos - range.start)
+            .collect();
+        sliced
+    }
+
+    /// Reverse complement, N stays N.
+    pub fn reverse_complement(&self) -> PackedDna {
+        let mut rc = PackedDna::with_capacity(self.len);
+        for pos in (0..self.len).rev() {
+            rc.push_code(3 - self.code(pos));
+        }
+        rc.n_positions = self
+            .n_positions
+            .iter()
+            .rev()
+            .map(|pos| self.len - 1 - pos)
+            .collect();
+        rc
+    }
+
+    /// Iterate over (start position, 2-bit encoded k-mer) pairs, k-mers overlapping an N are skipped.
+    /// The first base is stored in the highest bits. Panics if k is not in 1..=32.
+    pub fn kmers(&self, k: usize) -> Kmers<'_> {
+        assert!((1..=32).contains(&k), "k must be in 1..=32, got {}", k);
+        Kmers {
+            seq: self,
+            pos: 0,
+            next_n: 0,
+            encoder: KmerEncoder::new(k),
+        }
+    }
+
+    pub fn decode(&self) -> Vec<u8> {
+        let mut seq = Vec::with_capacity(self.len);
+        self.decode_into(&mut seq);
+        seq
+    }
+
+    /// Decode into buf (cleared first), so a buffer can be reused across alignments.
+    pub fn decode_into(&self, buf: &mut Vec<u8>) {
+        buf.clear();
+        buf.extend((0..self.len).map(|pos| NT4_TO_BASE[self.code(pos) as usize]));
+        for &pos in &self.n_positions {
+            buf[pos] = b'N';
+        }
+    }
+}
+
+impl From<&[u8]> for PackedDna {
+    fn from(seq: &[u8]) -> Self {
+        let mut packed = PackedDna::with_capacity(seq.len());
+        seq.iter().for_each(|&base| packed.push(base));
+        packed
+    }
+}
+
+/// Rolling 2-bit code of the last k bases, the first base in the highest bits.
+/// A base code above 3 (N) restarts the k-mer. k must be in 1..=32.
+#[derive(Debug, Clone)]
+pub(crate) struct KmerEncoder {
+    k: usize,
+    mask: u64,
+    valid: usize,
+    code: u64,
+}
+
+impl KmerEncoder {
+    pub(crate) fn new(k: usize) -> Self {
+        KmerEncoder {
+            k,
+            mask: if k == 32 {
+                u64::MAX
+            } else {
+                (1u64 << (2 * k)) - 1
+            },
+            valid: 0,
+            code: 0,
+        }
+    }
+
+    /// Add the next base code (see `SEQ_NT4_TABLE`), returns the k-mer ending at it if the
+    /// last k bases are all ACGT.
+    pub(crate) fn push(&mut self, base: u8) -> Option<u64> {
+        if base > 3 {
+            self.valid = 0;
+            self.code = 0;
+            return None;
+        }
+        self.code = ((self.code << 2) | base as u64) & self.mask;
+        self.valid += 1;
+        (self.valid >= self.k).then_some(self.code)
+    }
+}
+
+pub struct Kmers<'a> {
+    seq: &'a PackedDna,
+    pos: usize,
+    next_n: usize,
+    encoder: KmerEncoder,
+}
+
+impl Iterator for Kmers<'_> {
+    type Item = (usize, u64);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.pos < self.seq.len {
+            let pos = self.pos;
+            self.pos += 1;
+            let base = if self.seq.n_positions.get(self.next_n) == Some(&pos) {
+                self.next_n += 1;
+                4
+            } else {
+                self.seq.code(pos)
+            };
+            if let Some(code) = self.encoder.push(base) {
+                return Some((pos + 1 - self.encoder.k, code));
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::reverse_complement;
+
+    #[test]
+    fn test_packed_dna_round_trip() {
+        let seq = b"ACGTNacgtACGTACGTACGTACGTACGTACGTTTGCAN";
+        let packed = PackedDna::from(&seq[..]);
+        assert_eq!(packed.len(), seq.len());
+        assert_eq!(packed.decode(), seq.to_ascii_uppercase());
+        assert_eq!(packed.get(4), Some(b'N'));
+        assert_eq!(packed.get(seq.len()), None);
+
+        assert_eq!(
+            packed.slice(3..36).decode(),
+            &seq.to_ascii_uppercase()[3..36]
+        );
+        assert_eq!(
+            packed.reverse_complement().decode(),
+            reverse_complement(&seq.to_ascii_uppercase())
+        );
+    }
+
+    #[test]
+    fn test_packed_dna_kmers() {
+        let packed = PackedDna::from(&b"ACGTNCGT"[..]);
+        let kmers: Vec<(usize, u64)> = packed.kmers(3).collect();
+        assert_eq!(kmers, vec![(0, 0b000110), (1, 0b011011), (5, 0b011011)]);
+    }
+}
@@ -1,12 +1,16 @@
-use std::ffi::c_char;
+use std::{ffi::c_char, fmt, str::FromStr};
 
 use crate::edlib_sys::EdlibEqualityPair;
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AlignMode {
     Global,
     Prefix,
     Infix,
+    /// Gap at target start is not penalized, gap at target end is.
+    /// Not an edlib mode: query and target are reversed and aligned in prefix mode.
+    Suffix,
 }
 
 impl Default for AlignMode {
@@ -15,7 +19,36 @@ impl Default for AlignMode {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl fmt::Display for AlignMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AlignMode::Global => "global",
+            AlignMode::Prefix => "prefix",
+            AlignMode::Infix => "infix",
+            AlignMode::Suffix => "suffix",
+        })
+    }
+}
+
+/// Accepts the mode names and edlib's names (nw, shw, hw), case insensitive.
+impl FromStr for AlignMode {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "global" | "nw" => Ok(AlignMode::Global),
+            "prefix" | "shw" => Ok(AlignMode::Prefix),
+            "infix" | "hw" => Ok(AlignMode::Infix),
+            "suffix" => Ok(AlignMode::Suffix),
+            _ => Err(format!(
+                "invalid align mode {:?}, expected one of: global (nw), prefix (shw), infix (hw), suffix",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum AlignTask {
     Distance,
     Locations,
@@ -28,7 +61,34 @@ impl Default for AlignTask {
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+impl fmt::Display for AlignTask {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AlignTask::Distance => "distance",
+            AlignTask::Locations => "locations",
+            AlignTask::Path => "path",
+        })
+    }
+}
+
+/// Accepts the task names and edlib's names (loc), case insensitive.
+impl FromStr for AlignTask {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "distance" => Ok(AlignTask::Distance),
+            "locations" | "loc" => Ok(AlignTask::Locations),
+            "path" => Ok(AlignTask::Path),
+            _ => Err(format!(
+                "invalid align task {:?}, expected one of: distance, locations (loc), path",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum CigarFmt {
     NoCigar,
     Standard,
@@ -41,6 +101,34 @@ impl Default for CigarFmt {
     }
 }
 
+impl fmt::Display for CigarFmt {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            CigarFmt::NoCigar => "none",
+            CigarFmt::Standard => "standard",
+            CigarFmt::Extended => "extended",
+        })
+    }
+}
+
+/// Case insensitive.
+impl FromStr for CigarFmt {
+    type Err = String;
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" | "nocigar" => Ok(CigarFmt::NoCigar),
+            "standard" => Ok(CigarFmt::Standard),
+            "extended" => Ok(CigarFmt::Extended),
+            _ => Err(format!(
+                "invalid cigar format {:?}, expected one of: none (nocigar), standard, extended",
+                s
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct EdlibAlignParam {
     ///  Set k to non-negative value to tell edlib that edit distance is not larger than k
     ///  Smaller k can significantly improve speed of computation.
@@ -63,7 +151,21 @@ pub struct EdlibAlignParam {
     ///  This can be useful if you have some wildcard characters that should match multiple other characters,
     ///  or e.g. if you want edlib to be case insensitive.
     ///  Can be set to NULL if there are none.
+    #[cfg_attr(feature = "serde", serde(with = "eq_pairs_serde"))]
     additional_eq_pairs: Vec<EdlibEqualityPair>,
+
+    ///  Merge runs of adjacent end locations into one location per hit,
+    ///  see `EdlibAlignResult::collapse_locations`. Off by default.
+    #[cfg_attr(feature = "serde", serde(default))]
+    collapse_locations: bool,
+
+    ///  Maximal edit distance per query base. If set, k is additionally capped to
+    ///  floor(rate * query length) for each alignment, see `effective_k`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, deserialize_with = "deserialize_error_rate")
+    )]
+    max_error_rate: Option<f32>,
 }
 
 impl EdlibAlignParam {
@@ -74,6 +176,8 @@ impl EdlibAlignParam {
             task,
             cigar_fmt: CigarFmt::NoCigar,
             additional_eq_pairs: Vec::new(),
+            collapse_locations: false,
+            max_error_rate: None,
         }
     }
     pub fn set_eq_pairs(&mut self, eq_pairs: Vec<(u8, u8)>) {
@@ -128,8 +232,120 @@ impl EdlibAlignParam {
     pub fn additional_eq_pairs(&self) -> &[EdlibEqualityPair] {
         &self.additional_eq_pairs
     }
+
+    /// `table[a][b]` is true if bytes a and b are equal under the additional equality pairs,
+    /// for the pure Rust aligners.
+    pub(crate) fn equality_table(&self) -> [[bool; 256]; 256] {
+        let mut equal = [[false; 256]; 256];
+        for (c, row) in equal.iter_mut().enumerate() {
+            row[c] = true;
+        }
+        for pair in &self.additional_eq_pairs {
+            equal[pair.first as u8 as usize][pair.second as u8 as usize] = true;
+            equal[pair.second as u8 as usize][pair.first as u8 as usize] = true;
+        }
+        equal
+    }
+
+    pub fn set_collapse_locations(&mut self, collapse_locations: bool) {
+        self.collapse_locations = collapse_locations;
+    }
+
+    pub fn collapse_locations(&self) -> bool {
+        self.collapse_locations
+    }
+
+    /// Derive k from the query length instead of fixing it, e.g. 0.1 allows one edit per
+    /// ten query bases. Panics if rate is not within [0, 1].
+    pub fn set_max_error_rate(&mut self, rate: f32) {
+        if let Err(err) = check_error_rate(rate) {
+            panic!("{}", err);
+        }
+        self.max_error_rate = Some(rate);
+    }
+
+    pub fn max_error_rate(&self) -> Option<f32> {
+        self.max_error_rate
+    }
+
+    /// k used to align a query of query_len bases: k, capped to floor(rate * query_len) if a
+    /// max error rate is set (a negative k then means no other limit).
+    /// The rate is relative to the query length in every mode, since the aligned length of an
+    /// infix hit is only known after aligning.
+    pub fn effective_k(&self, query_len: usize) -> i32 {
+        match self.max_error_rate {
+            None => self.k,
+            Some(rate) => {
+                let rate_k = max_edits(rate, query_len).floor() as i32;
+                if self.k < 0 {
+                    rate_k
+                } else {
+                    self.k.min(rate_k)
+                }
+            }
+        }
+    }
+
+    /// Whether edit_distance of a query of query_len bases satisfies the max error rate,
+    /// None if no rate is set.
+    pub(crate) fn within_error_rate(&self, edit_distance: i32, query_len: usize) -> Option<bool> {
+        self.max_error_rate.map(|rate| {
+            edit_distance >= 0 && f64::from(edit_distance) <= max_edits(rate, query_len)
+        })
+    }
 }
 
+/// rate * query_len, computed in f64 and nudged up by f32's relative rounding error, so a rate
+/// like 0.53 that f32 stores as 0.52999997 still allows 53 edits in 100 bases.
+fn max_edits(rate: f32, query_len: usize) -> f64 {
+    const F32_ROUNDING: f64 = 1e-7;
+    f64::from(rate) * query_len as f64 * (1.0 + F32_ROUNDING)
+}
+
+fn check_error_rate(rate: f32) -> Result<(), String> {
+    if (0.0..=1.0).contains(&rate) {
+        Ok(())
+    } else {
+        Err(format!(
+            "max error rate must be within [0, 1], got {}",
+            rate
+        ))
+    }
+}
+
+/// Deserialized rates go through the same check as `set_max_error_rate`.
+#[cfg(feature = "serde")]
+fn deserialize_error_rate<'de, D: serde::Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<f32>, D::Error> {
+    let rate = <Option<f32> as serde::Deserialize>::deserialize(deserializer)?;
+    if let Some(rate) = rate {
+        check_error_rate(rate).map_err(serde::de::Error::custom)?;
+    }
+    Ok(rate)
+}
+
+/// EdlibEqualityPair is a bindgen struct without PartialEq, so pairs are compared field by field.
+/// Error rates are compared by bits, so that Eq holds (they are never NaN).
+impl PartialEq for EdlibAlignParam {
+    fn eq(&self, other: &Self) -> bool {
+        self.k == other.k
+            && self.mode == other.mode
+            && self.task == other.task
+            && self.cigar_fmt == other.cigar_fmt
+            && self.collapse_locations == other.collapse_locations
+            && self.max_error_rate.map(f32::to_bits) == other.max_error_rate.map(f32::to_bits)
+            && self.additional_eq_pairs.len() == other.additional_eq_pairs.len()
+            && self
+                .additional_eq_pairs
+                .iter()
+                .zip(other.additional_eq_pairs.iter())
+                .all(|(a, b)| a.first == b.first && a.second == b.second)
+    }
+}
+
+impl Eq for EdlibAlignParam {}
+
 impl Default for EdlibAlignParam {
     fn default() -> Self {
         EdlibAlignParam {
@@ -138,6 +354,112 @@ impl Default for EdlibAlignParam {
             task: AlignTask::default(),
             cigar_fmt: CigarFmt::default(),
             additional_eq_pairs: Vec::new(),
+            collapse_locations: false,
+            max_error_rate: None,
+        }
+    }
+}
+
+/// (de)serialize equality pairs as byte tuples instead of the FFI struct
+#[cfg(feature = "serde")]
+mod eq_pairs_serde {
+    use std::ffi::c_char;
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::edlib_sys::EdlibEqualityPair;
+
+    pub fn serialize<S: Serializer>(
+        eq_pairs: &[EdlibEqualityPair],
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        eq_pairs
+            .iter()
+            .map(|pair| (pair.first as u8, pair.second as u8))
+            .collect::<Vec<_>>()
+            .serialize(serializer)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Vec<EdlibEqualityPair>, D::Error> {
+        let eq_pairs = Vec::<(u8, u8)>::deserialize(deserializer)?;
+        Ok(eq_pairs
+            .into_iter()
+            .map(|(a, b)| EdlibEqualityPair {
+                first: a as c_char,
+                second: b as c_char,
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_enum_str_round_trip() {
+        for mode in [
+            AlignMode::Global,
+            AlignMode::Prefix,
+            AlignMode::Infix,
+            AlignMode::Suffix,
+        ] {
+            assert_eq!(mode.to_string().parse::<AlignMode>().unwrap(), mode);
         }
+        for task in [AlignTask::Distance, AlignTask::Locations, AlignTask::Path] {
+            assert_eq!(task.to_string().parse::<AlignTask>().unwrap(), task);
+        }
+        for fmt in [CigarFmt::NoCigar, CigarFmt::Standard, CigarFmt::Extended] {
+            assert_eq!(fmt.to_string().parse::<CigarFmt>().unwrap(), fmt);
+        }
+
+        assert_eq!("HW".parse::<AlignMode>().unwrap(), AlignMode::Infix);
+        assert_eq!("shw".parse::<AlignMode>().unwrap(), AlignMode::Prefix);
+        assert_eq!("loc".parse::<AlignTask>().unwrap(), AlignTask::Locations);
+        assert!("semiglobal"
+            .parse::<AlignMode>()
+            .unwrap_err()
+            .contains("infix (hw)"));
+    }
+
+    #[test]
+    fn test_param_eq() {
+        let mut a = EdlibAlignParam::default();
+        a.add_eq_pair((b'N', b'A'));
+        let mut b = a.clone();
+        assert_eq!(a, b);
+        b.set_eq_pairs(vec![(b'N', b'C')]);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_effective_k() {
+        let mut param = EdlibAlignParam::default();
+        assert_eq!(param.effective_k(100), -1);
+        param.set_max_error_rate(0.1);
+        assert_eq!(param.effective_k(100), 10);
+        assert_eq!(param.effective_k(19), 1);
+        param.set_max_error_rate(0.7);
+        assert_eq!(param.effective_k(10), 7);
+        param.set_k(3);
+        assert_eq!(param.effective_k(10), 3);
+        assert_eq!(param.effective_k(2), 1);
+
+        // products that f32 rounds just below the integer
+        param.set_k(-1);
+        for (rate, len, k) in [(0.53, 100, 53), (0.42, 150, 63), (0.84, 75, 63)] {
+            param.set_max_error_rate(rate);
+            assert_eq!(param.effective_k(len), k, "{} * {}", rate, len);
+            assert_eq!(param.within_error_rate(k, len), Some(true));
+            assert_eq!(param.within_error_rate(k + 1, len), Some(false));
+        }
+
+        param.set_max_error_rate(0.7);
+        assert_eq!(param.within_error_rate(7, 10), Some(true));
+        assert_eq!(param.within_error_rate(8, 10), Some(false));
+        assert_eq!(param.within_error_rate(-1, 10), Some(false));
+        assert_eq!(EdlibAlignParam::default().within_error_rate(0, 10), None);
     }
 }
@@ -0,0 +1,227 @@
+use std::collections::HashMap;
+
+use crate::{
+    edlib_align, packed::KmerEncoder, param::EdlibAlignParam, utils::SEQ_NT4_TABLE,
+    EdlibAlignResult,
+};
+
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
+pub struct FilterStats {
+    /// number of targets tested
+    pub candidates: usize,
+    /// number of targets rejected without alignment
+    pub filtered: usize,
+}
+
+impl FilterStats {
+    pub fn passed(&self) -> usize {
+        self.candidates - self.filtered
+    }
+}
+
+/// q-gram counting filter for one query against many targets.
+///
+/// By the q-gram lemma, a target within k edits of the query (or containing such a region)
+/// shares at least `|query| - q + 1 - k * q` q-grams with it, so targets sharing fewer can be skipped.
+/// Bases are 2-bit encoded with `SEQ_NT4_TABLE`, q-grams containing anything but ACGT are ignored.
+/// The lemma assumes plain base equality: with additional equality pairs true hits may be rejected.
+pub struct QgramFilter {
+    q: usize,
+    /// index of every distinct query q-gram into the count vectors
+    query_qgrams: HashMap<u64, usize>,
+    query_counts: Vec<u32>,
+    /// query counts not yet matched by the current target, reset after each target
+    remaining: Vec<u32>,
+    /// indices decremented by the current target
+    touched: Vec<usize>,
+    min_shared: i64,
+    stats: FilterStats,
+}
+
+impl QgramFilter {
+    pub fn new(query: &[u8], q: usize, k: usize) -> Result<Self, String> {
+        if q == 0 || q > 32 {
+            return Err(format!("q must be in 1..=32, got {}", q));
+        }
+
+        let mut query_qgrams = HashMap::new();
+        let mut query_counts = Vec::new();
+        for qgram in Qgrams::new(query, q) {
+            let idx = *query_qgrams.entry(qgram).or_insert_with(|| {
+                query_counts.push(0);
+                query_counts.len() - 1
+            });
+            query_counts[idx] += 1;
+        }
+        let num_qgrams: u32 = query_counts.iter().sum();
+
+        Ok(QgramFilter {
+            q,
+            query_qgrams,
+            remaining: query_counts.clone(),
+            query_counts,
+            touched: Vec::new(),
+            min_shared: num_qgrams as i64 - (k * q) as i64,
+            stats: FilterStats::default(),
+        })
+    }
+
+    /// Returns false if target can not contain a match within k edits of the query.
+    pub fn may_match(&mut self, target: &[u8]) -> bool {
+        self.stats.candidates += 1;
+        if self.min_shared <= 0 {
+            return true;
+        }
+
+        let passed = self.count_shared(target);
+        for &idx in &self.touched {
+            self.remaining[idx] = self.query_counts[idx];
+        }
+        self.touched.clear();
+
+        if !passed {
+            self.stats.filtered += 1;
+        }
+        passed
+    }
+
+    /// Whether target shares at least min_shared q-grams, counted down in `remaining`.
+    fn count_shared(&mut self, target: &[u8]) -> bool {
+        let mut shared = 0;
+        for qgram in Qgrams::new(target, self.q) {
+            let Some(&idx) = self.query_qgrams.get(&qgram) else {
+                continue;
+            };
+            if self.remaining[idx] > 0 {
+                if self.remaining[idx] == self.query_counts[idx] {
+                    self.touched.push(idx);
+                }
+                self.remaining[idx] -= 1;
+                shared += 1;
+                if shared >= self.min_shared {
+                    return true;
+                }
+            }
+        }
+        false
+    }
+
+    pub fn stats(&self) -> FilterStats {
+        self.stats
+    }
+
+    pub fn reset_stats(&mut self) {
+        self.stats = FilterStats::default();
+    }
+}
+
+/// 2-bit encoded q-grams of a sequence, skipping those that contain a non-ACGT base.
+struct Qgrams<'a> {
+    seq: std::slice::Iter<'a, u8>,
+    encoder: KmerEncoder,
+}
+
+impl<'a> Qgrams<'a> {
+    fn new(seq: &'a [u8], q: usize) -> Self {
+        Qgrams {
+            seq: seq.iter(),
+            encoder: KmerEncoder::new(q),
+        }
+    }
+}
+
+impl Iterator for Qgrams<'_> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let encoder = &mut self.encoder;
+        self.seq
+            .find_map(|&base| encoder.push(SEQ_NT4_TABLE[base as usize]))
+    }
+}
+
+/// Align query against every target that passes the q-gram filter.
+/// The k of `aln_param` (capped by its max error rate) must be non-negative,
+/// it is the k the filter is built for.
+/// Returns the (target index, result) pairs within k edits, and the filter statistics.
+pub fn filter_align(
+    query: &[u8],
+    targets: &[&[u8]],
+    q: usize,
+    aln_param: &EdlibAlignParam,
+) -> Result<(Vec<(usize, EdlibAlignResult)>, FilterStats), String> {
+    let k = aln_param.effective_k(query.len());
+    if k < 0 {
+        return Err("q-gram filtering requires a non-negative k".to_string());
+    }
+
+    let mut filter = QgramFilter::new(query, q, k as usize)?;
+    let mut hits = Vec::new();
+    for (idx, target) in targets.iter().enumerate() {
+        if !filter.may_match(target) {
+            continue;
+        }
+        let aln_res = edlib_align(query, target, aln_param)?;
+        if aln_res.edit_distance >= 0 {
+            hits.push((idx, aln_res));
+        }
+    }
+
+    Ok((hits, filter.stats()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{AlignMode, AlignTask};
+
+    #[test]
+    fn test_qgrams_skip_n() {
+        let qgrams: Vec<u64> = Qgrams::new(b"ACGNACGT", 3).collect();
+        assert_eq!(qgrams, vec![0b000110, 0b000110, 0b011011]);
+    }
+
+    #[test]
+    fn test_filter_align() {
+        let query = b"ACGTTGCAAGCTTACG";
+        let targets: [&[u8]; 3] = [
+            b"TTTTTACGTTGCATGCTTACGTTTTT",
+            b"GGGGGGGGGGGGGGGGGGGGGGGGGG",
+            b"ACGTTGCAAGCTTACG",
+        ];
+        let param = EdlibAlignParam::new(2, AlignMode::Infix, AlignTask::Distance);
+        let (hits, stats) = filter_align(query, &targets, 4, &param).unwrap();
+
+        assert_eq!(
+            hits.iter()
+                .map(|(idx, res)| (*idx, res.edit_distance))
+                .collect::<Vec<_>>(),
+            vec![(0, 1), (2, 0)]
+        );
+        assert_eq!(
+            stats,
+            FilterStats {
+                candidates: 3,
+                filtered: 1
+            }
+        );
+    }
+
+    #[test]
+    fn test_may_match_resets_counts() {
+        // the query has AAAA twice, one target may match it only once
+        let mut filter = QgramFilter::new(b"AAAAACGT", 4, 0).unwrap();
+        assert!(filter.may_match(b"AAAAACGT"));
+        assert!(!filter.may_match(b"AAAACGTT"));
+        assert!(filter.may_match(b"TTAAAAACGT"));
+        assert!(!filter.may_match(b"GGGGGGGG"));
+        assert!(filter.may_match(b"AAAAACGT"));
+        assert_eq!(
+            filter.stats(),
+            FilterStats {
+                candidates: 5,
+                filtered: 2
+            }
+        );
+    }
+}
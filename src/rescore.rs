@@ -0,0 +1,129 @@
+use crate::{
+    cigar::{parse_cigar, AlignOp},
+    dp::Scoring,
+    EdlibAlignResult,
+};
+
+/// Cost of a cigar path that starts at target_start under scoring, without realigning.
+/// Runs of the same gap op are one gap, a gap split by a run of the other gap op is two.
+pub fn score_path(
+    query: &[u8],
+    target: &[u8],
+    target_start: usize,
+    ops: &[(AlignOp, usize)],
+    scoring: &Scoring,
+) -> Result<i32, String> {
+    let mut query_pos = 0;
+    let mut target_pos = target_start;
+    let mut score = 0;
+    let mut prev_op = None;
+    for &(op, len) in ops {
+        let query_end = query_pos + if op.consumes_query() { len } else { 0 };
+        let target_end = target_pos + if op.consumes_target() { len } else { 0 };
+        if query_end > query.len() || target_end > target.len() {
+            return Err(format!(
+                "path runs past the sequences at query {}, target {}",
+                query_pos, target_pos
+            ));
+        }
+
+        match op {
+            AlignOp::Match | AlignOp::Mismatch => {
+                score += query[query_pos..query_end]
+                    .iter()
+                    .zip(&target[target_pos..target_end])
+                    .map(|(&q, &t)| scoring.substitution(q, t))
+                    .sum::<i32>();
+            }
+            AlignOp::Insertion | AlignOp::Deletion => {
+                if prev_op != Some(op) {
+                    score += scoring.gap_open;
+                }
+                score += len as i32 * scoring.gap_extend;
+            }
+        }
+        prev_op = Some(op);
+        query_pos = query_end;
+        target_pos = target_end;
+    }
+
+    if query_pos != query.len() {
+        return Err(format!(
+            "path covers {} of {} query bases",
+            query_pos,
+            query.len()
+        ));
+    }
+    Ok(score)
+}
+
+/// Cost of the alignment path of an `AlignTask::Path` result (standard or extended cigar)
+/// under the substitution costs and affine gaps of scoring, e.g. to rank several edlib hits.
+/// These are the costs `dp_align` minimizes, so an alignment of it rescores to its
+/// `edit_distance` (unless equality pairs were used, which are not known here).
+pub fn rescore(
+    query: &[u8],
+    target: &[u8],
+    aln_res: &EdlibAlignResult,
+    scoring: &Scoring,
+) -> Result<i32, String> {
+    let cigar = aln_res
+        .cigar
+        .as_deref()
+        .ok_or("result has no cigar, align with AlignTask::Path and a cigar format")?;
+    let &(start, _) = aln_res.locations.first().ok_or("result has no location")?;
+    score_path(query, target, start, &parse_cigar(cigar)?, scoring)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        dp::dp_align,
+        edlib_align,
+        param::{AlignMode, AlignTask, CigarFmt, EdlibAlignParam},
+    };
+
+    #[test]
+    fn test_rescore() {
+        let mut scoring = Scoring::new(0, 3, 5, 2);
+        scoring.set_substitution(b'A', b'G', 1);
+
+        let mut param = EdlibAlignParam::new(-1, AlignMode::Infix, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Standard);
+        let aln_res = edlib_align(b"ACGTAAGT", b"TTACGTGAGTTT", &param).unwrap();
+        // 7 matches, one A/G transition
+        assert_eq!(
+            rescore(b"ACGTAAGT", b"TTACGTGAGTTT", &aln_res, &scoring).unwrap(),
+            1
+        );
+
+        let ops = [
+            (AlignOp::Match, 2),
+            (AlignOp::Deletion, 2),
+            (AlignOp::Match, 2),
+            (AlignOp::Insertion, 1),
+        ];
+        // 4 matches, a gap of 2 and a gap of 1
+        assert_eq!(
+            score_path(b"ACGTA", b"ACTTGT", 0, &ops, &scoring).unwrap(),
+            16
+        );
+        assert!(score_path(b"ACGTA", b"ACTTG", 0, &ops, &scoring).is_err());
+    }
+
+    #[test]
+    fn test_rescore_dp_align() {
+        let mut scoring = Scoring::new(0, 4, 5, 1);
+        scoring.set_substitution(b'A', b'G', 2);
+        let mut param = EdlibAlignParam::new(-1, AlignMode::Infix, AlignTask::Path);
+        param.set_cigar_fmt(CigarFmt::Extended);
+
+        let (query, target) = (b"CCACGGGGTTAC", b"AAACCGCGGTTTTTACAAA");
+        let aln_res = dp_align(query, target, &param, &scoring).unwrap();
+        assert_eq!(
+            rescore(query, target, &aln_res, &scoring).unwrap(),
+            aln_res.edit_distance
+        );
+    }
+}
@@ -0,0 +1,123 @@
+use std::collections::BinaryHeap;
+
+use crate::{
+    edlib_align,
+    param::{AlignTask, EdlibAlignParam},
+};
+
+/// A collection of sequences that can be searched for the entries closest to a query.
+/// Every entry is aligned as target with the query, using the mode, k and equality pairs of `aln_param`.
+pub struct SeqIndex {
+    seqs: Vec<Vec<u8>>,
+    aln_param: EdlibAlignParam,
+}
+
+impl SeqIndex {
+    pub fn new(aln_param: EdlibAlignParam) -> Self {
+        SeqIndex {
+            seqs: Vec::new(),
+            aln_param,
+        }
+    }
+
+    pub fn from_seqs<S: AsRef<[u8]>>(seqs: &[S], aln_param: EdlibAlignParam) -> Self {
+        SeqIndex {
+            seqs: seqs.iter().map(|seq| seq.as_ref().to_vec()).collect(),
+            aln_param,
+        }
+    }
+
+    /// Add a sequence and return its index.
+    pub fn add(&mut self, seq: &[u8]) -> usize {
+        self.seqs.push(seq.to_vec());
+        self.seqs.len() - 1
+    }
+
+    pub fn get(&self, idx: usize) -> Option<&[u8]> {
+        self.seqs.get(idx).map(|seq| seq.as_slice())
+    }
+
+    pub fn len(&self) -> usize {
+        self.seqs.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.seqs.is_empty()
+    }
+
+    /// Find the n entries closest to query.
+    /// Returns (index, edit_distance) pairs sorted by distance, ties broken by index.
+    /// Once n candidates are collected, k is tightened to the current n-th best distance,
+    /// so hopeless entries are rejected early by edlib.
+    pub fn nearest(&self, query: &[u8], n: usize) -> Result<Vec<(usize, usize)>, String> {
+        let mut aln_param = self.aln_param.clone();
+        aln_param.set_task(AlignTask::Distance);
+        let user_k = self.aln_param.k();
+
+        // max-heap on (distance, index): the top is the worst of the current best n
+        let mut best: BinaryHeap<(usize, usize)> = BinaryHeap::new();
+        for (idx, seq) in self.seqs.iter().enumerate() {
+            let k = if best.len() == n {
+                // later entries only win with a strictly smaller distance
+                match best.peek() {
+                    Some(&(0, _)) | None => break,
+                    Some(&(worst, _)) => worst as i32 - 1,
+                }
+            } else {
+                user_k
+            };
+            aln_param.set_k(if user_k >= 0 { k.min(user_k) } else { k });
+
+            let aln_res = edlib_align(query, seq, &aln_param)?;
+            if aln_res.edit_distance < 0 {
+                continue;
+            }
+            best.push((aln_res.edit_distance as usize, idx));
+            if best.len() > n {
+                best.pop();
+            }
+        }
+
+        Ok(best
+            .into_sorted_vec()
+            .into_iter()
+            .map(|(dist, idx)| (idx, dist))
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seq_index_nearest() {
+        let whitelist: [&[u8]; 5] = [
+            b"AAAACCCC",
+            b"AAAACCCG",
+            b"GGGGTTTT",
+            b"AAAACCGG",
+            b"TTTTTTTT",
+        ];
+        let index = SeqIndex::from_seqs(&whitelist, EdlibAlignParam::default());
+
+        let hits = index.nearest(b"AAAACCCC", 3).unwrap();
+        assert_eq!(hits, vec![(0, 0), (1, 1), (3, 2)]);
+
+        let hits = index.nearest(b"GGGGTTTA", 1).unwrap();
+        assert_eq!(hits, vec![(2, 1)]);
+        assert!(index.nearest(b"GGGGTTTA", 0).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_seq_index_respects_k() {
+        let mut param = EdlibAlignParam::default();
+        param.set_k(1);
+        let mut index = SeqIndex::new(param);
+        index.add(b"ACGTACGT");
+        index.add(b"ACGTTTTT");
+
+        let hits = index.nearest(b"ACGTACGA", 2).unwrap();
+        assert_eq!(hits, vec![(0, 1)]);
+    }
+}
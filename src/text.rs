@@ -0,0 +1,107 @@
+use std::collections::HashSet;
+
+use crate::{
+    generic::{edlib_align_generic_with_eq_pairs, MAX_ALPHABET_SIZE},
+    param::EdlibAlignParam,
+    EdlibAlignResult,
+};
+
+/// Align two strings by Unicode scalar values instead of bytes.
+/// Locations and cigar are expressed in char indices.
+/// Both strings together can use at most `MAX_ALPHABET_SIZE` distinct chars, as edlib
+/// aligns bytes; larger alphabets are rejected with an error.
+pub fn align_str(
+    query: &str,
+    target: &str,
+    aln_param: &EdlibAlignParam,
+) -> Result<EdlibAlignResult, String> {
+    align_str_with_eq_pairs(query, target, &[], aln_param)
+}
+
+/// `align_str` with additional equality pairs on chars, e.g. to ignore case.
+pub fn align_str_with_eq_pairs(
+    query: &str,
+    target: &str,
+    eq_pairs: &[(char, char)],
+    aln_param: &EdlibAlignParam,
+) -> Result<EdlibAlignResult, String> {
+    let query: Vec<char> = query.chars().collect();
+    let target: Vec<char> = target.chars().collect();
+    edlib_align_generic_with_eq_pairs(&query, &target, eq_pairs, aln_param)
+}
+
+/// Levenshtein distance between two strings, counted in chars.
+/// Strings with more than `MAX_ALPHABET_SIZE` distinct chars together are compared with a
+/// plain O(nm) DP instead of edlib.
+pub fn levenshtein_str(a: &str, b: &str) -> Result<usize, String> {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let alphabet: HashSet<char> = a.iter().chain(b.iter()).copied().collect();
+    if alphabet.len() > MAX_ALPHABET_SIZE {
+        return Ok(levenshtein_dp(&a, &b));
+    }
+
+    let aln_res = edlib_align_generic_with_eq_pairs(&a, &b, &[], &EdlibAlignParam::default())?;
+    Ok(aln_res.edit_distance as usize)
+}
+
+/// Two row Levenshtein DP.
+fn levenshtein_dp(a: &[char], b: &[char]) -> usize {
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, &ca) in a.iter().enumerate() {
+        let mut next = vec![i + 1; b.len() + 1];
+        for (j, &cb) in b.iter().enumerate() {
+            next[j + 1] = (row[j] + usize::from(ca != cb))
+                .min(row[j + 1] + 1)
+                .min(next[j] + 1);
+        }
+        row = next;
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::param::{AlignMode, AlignTask};
+
+    #[test]
+    fn test_levenshtein_str() {
+        assert_eq!(levenshtein_str("naïve café", "naive cafe").unwrap(), 2);
+        assert_eq!(levenshtein_str("", "日本語").unwrap(), 3);
+    }
+
+    #[test]
+    fn test_levenshtein_str_large_alphabet() {
+        let a: String = (0x4e00..0x4e00 + 300).filter_map(char::from_u32).collect();
+        let mut b: String = a.chars().skip(1).collect();
+        b.push('x');
+        assert_eq!(levenshtein_str(&a, &b).unwrap(), 2);
+        assert!(align_str(&a, &b, &EdlibAlignParam::default()).is_err());
+
+        let a: Vec<char> = "kitten".chars().collect();
+        let b: Vec<char> = "sitting".chars().collect();
+        assert_eq!(levenshtein_dp(&a, &b), 3);
+    }
+
+    #[test]
+    fn test_align_str_char_locations() {
+        let param = EdlibAlignParam::new(-1, AlignMode::Infix, AlignTask::Locations);
+        let aln_res = align_str("café", "un café noir", &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 0);
+        assert_eq!(aln_res.locations, vec![(3, 6)]);
+    }
+
+    #[test]
+    fn test_align_str_case_insensitive() {
+        let param = EdlibAlignParam::default();
+        let eq_pairs: Vec<(char, char)> =
+            ('a'..='z').map(|c| (c, c.to_ascii_uppercase())).collect();
+        let aln_res = align_str_with_eq_pairs("Hello", "hELLO", &eq_pairs, &param).unwrap();
+        assert_eq!(aln_res.edit_distance, 0);
+        assert_eq!(
+            align_str("Hello", "hELLO", &param).unwrap().edit_distance,
+            5
+        );
+    }
+}
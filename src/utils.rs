@@ -1,3 +1,5 @@
+use std::thread;
+
 pub static COMPLEMENT_TABLE: [u8; 256] = {
     let mut table = [0u8; 256];
     table[b'A' as usize] = b'T';
@@ -32,3 +34,38 @@ pub fn reverse_complement(dna: &[u8]) -> Vec<u8> {
     }
     result
 }
+
+/// Map f over items 0..num_items on `threads` scoped threads, dealing the items round robin,
+/// and return the results in item order or the first error. Panics if a worker panics.
+pub(crate) fn parallel_map<T, F>(num_items: usize, threads: usize, f: F) -> Result<Vec<T>, String>
+where
+    T: Send,
+    F: Fn(usize) -> Result<T, String> + Sync,
+{
+    let threads = threads.clamp(1, num_items.max(1));
+    let f = &f;
+    let per_thread: Vec<Result<Vec<(usize, T)>, String>> = thread::scope(|s| {
+        let handles: Vec<_> = (0..threads)
+            .map(|tid| {
+                s.spawn(move || {
+                    (tid..num_items)
+                        .step_by(threads)
+                        .map(|i| Ok((i, f(i)?)))
+                        .collect()
+                })
+            })
+            .collect();
+        handles
+            .into_iter()
+            .map(|handle| handle.join().expect("worker thread panicked"))
+            .collect()
+    });
+
+    let mut results: Vec<Option<T>> = (0..num_items).map(|_| None).collect();
+    for thread_results in per_thread {
+        for (i, result) in thread_results? {
+            results[i] = Some(result);
+        }
+    }
+    Ok(results.into_iter().map(|result| result.unwrap()).collect())
+}
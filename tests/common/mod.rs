@@ -0,0 +1,204 @@
+//! O(nm) reference DP used to cross-check `edlib_align`, shared by the property tests and the fuzz target.
+
+use rsedlib::{
+    cigar::{parse_cigar, AlignOp},
+    edlib_align,
+    param::{AlignMode, AlignTask, CigarFmt, EdlibAlignParam},
+};
+
+fn is_equal(a: u8, b: u8, eq_pairs: &[(u8, u8)]) -> bool {
+    a == b
+        || eq_pairs
+            .iter()
+            .any(|&(x, y)| (x == a && y == b) || (x == b && y == a))
+}
+
+/// Last DP row: cost of aligning the whole query to target[..j] (to target[i..j] for infix).
+fn last_row(query: &[u8], target: &[u8], free_start: bool, eq_pairs: &[(u8, u8)]) -> Vec<usize> {
+    let mut row: Vec<usize> = (0..=target.len())
+        .map(|j| if free_start { 0 } else { j })
+        .collect();
+    for (i, &q) in query.iter().enumerate() {
+        let mut next = vec![i + 1; target.len() + 1];
+        for (j, &t) in target.iter().enumerate() {
+            let diag = row[j] + usize::from(!is_equal(q, t, eq_pairs));
+            next[j + 1] = diag.min(row[j + 1] + 1).min(next[j] + 1);
+        }
+        row = next;
+    }
+    row
+}
+
+pub fn global_distance(query: &[u8], target: &[u8], eq_pairs: &[(u8, u8)]) -> usize {
+    *last_row(query, target, false, eq_pairs).last().unwrap()
+}
+
+/// Reference `distance_profile`: the last row without the empty target column.
+pub fn reference_profile(
+    query: &[u8],
+    target: &[u8],
+    mode: AlignMode,
+    eq_pairs: &[(u8, u8)],
+) -> Vec<usize> {
+    let free_start = matches!(mode, AlignMode::Infix | AlignMode::Suffix);
+    last_row(query, target, free_start, eq_pairs)[1..].to_vec()
+}
+
+/// Reference edit distance and all optimal end positions in target.
+pub fn reference_align(
+    query: &[u8],
+    target: &[u8],
+    mode: AlignMode,
+    eq_pairs: &[(u8, u8)],
+) -> (usize, Vec<usize>) {
+    let free_start = matches!(mode, AlignMode::Infix | AlignMode::Suffix);
+    let row = last_row(query, target, free_start, eq_pairs);
+    if matches!(mode, AlignMode::Global | AlignMode::Suffix) {
+        return (row[target.len()], vec![target.len() - 1]);
+    }
+    let best = *row[1..].iter().min().unwrap();
+    let ends = (1..=target.len())
+        .filter(|&j| row[j] == best)
+        .map(|j| j - 1)
+        .collect();
+    (best, ends)
+}
+
+/// Align with edlib and check distance, locations and cigar against the reference DP.
+/// query and target must not be empty.
+pub fn check_against_reference(
+    query: &[u8],
+    target: &[u8],
+    aln_param: &EdlibAlignParam,
+    eq_pairs: &[(u8, u8)],
+) -> Result<(), String> {
+    let aln_res = edlib_align(query, target, aln_param)?;
+    let (dist, ends) = reference_align(query, target, aln_param.mode(), eq_pairs);
+
+    let mut alphabet: Vec<u8> = query.iter().chain(target.iter()).copied().collect();
+    alphabet.sort_unstable();
+    alphabet.dedup();
+    if aln_res.alphabet_length as usize != alphabet.len() {
+        return Err(format!(
+            "alphabet length {} != {}",
+            aln_res.alphabet_length,
+            alphabet.len()
+        ));
+    }
+
+    if aln_param.k() >= 0 && dist > aln_param.k() as usize {
+        if aln_res.edit_distance != -1 || !aln_res.locations.is_empty() {
+            return Err(format!(
+                "distance {} over k {} reported as {:?}",
+                dist,
+                aln_param.k(),
+                aln_res
+            ));
+        }
+        return Ok(());
+    }
+    if aln_res.edit_distance != dist as i32 {
+        return Err(format!(
+            "edit distance {} != reference {}",
+            aln_res.edit_distance, dist
+        ));
+    }
+
+    // edlib also reports the end -1 in infix mode if deleting the whole query is optimal
+    let whole_query_deleted = |&(_, end): &(usize, usize)| end == usize::MAX;
+    let mut res_ends: Vec<usize> = aln_res
+        .locations
+        .iter()
+        .filter(|location| !whole_query_deleted(location))
+        .map(|&(_, end)| end)
+        .collect();
+    // suffix alignments all end at the target end, one location per optimal start
+    res_ends.dedup();
+    if res_ends != ends {
+        return Err(format!(
+            "end locations {:?} != reference {:?}",
+            res_ends, ends
+        ));
+    }
+    if aln_param.task() == AlignTask::Distance && aln_param.mode() != AlignMode::Suffix {
+        return Ok(());
+    }
+
+    if aln_res.locations.iter().any(whole_query_deleted) && dist != query.len() {
+        return Err(format!("end -1 reported at distance {}", dist));
+    }
+    for &(start, end) in aln_res.locations.iter().filter(|l| !whole_query_deleted(l)) {
+        let anchored = matches!(aln_param.mode(), AlignMode::Global | AlignMode::Prefix);
+        if anchored && start != 0 {
+            return Err(format!("start {} of anchored alignment is not 0", start));
+        }
+        if start > end || global_distance(query, &target[start..=end], eq_pairs) != dist {
+            return Err(format!(
+                "location ({}, {}) does not align at distance {}",
+                start, end, dist
+            ));
+        }
+    }
+
+    if aln_param.task() == AlignTask::Path && aln_param.cigar_fmt() == CigarFmt::Extended {
+        let cigar = aln_res.cigar.as_deref().ok_or("missing cigar")?;
+        if whole_query_deleted(&aln_res.locations[0]) {
+            let deleted = format!("{}I", query.len());
+            if cigar != deleted {
+                return Err(format!("cigar {} of end -1 != {}", cigar, deleted));
+            }
+            return Ok(());
+        }
+        check_cigar(query, target, aln_res.locations[0], cigar, dist, eq_pairs)?;
+    }
+
+    Ok(())
+}
+
+fn check_cigar(
+    query: &[u8],
+    target: &[u8],
+    (start, end): (usize, usize),
+    cigar: &str,
+    dist: usize,
+    eq_pairs: &[(u8, u8)],
+) -> Result<(), String> {
+    let mut query_pos = 0;
+    let mut target_pos = start;
+    let mut edits = 0;
+    for (op, len) in parse_cigar(cigar)? {
+        for _ in 0..len {
+            if op.consumes_query() && query_pos >= query.len()
+                || op.consumes_target() && target_pos > end
+            {
+                return Err(format!("cigar {} runs past the alignment", cigar));
+            }
+            match op {
+                AlignOp::Match | AlignOp::Mismatch => {
+                    let equal = is_equal(query[query_pos], target[target_pos], eq_pairs);
+                    if equal != (op == AlignOp::Match) {
+                        return Err(format!(
+                            "cigar {} has wrong {:?} at query {}",
+                            cigar, op, query_pos
+                        ));
+                    }
+                }
+                AlignOp::Insertion | AlignOp::Deletion => {}
+            }
+            edits += usize::from(op != AlignOp::Match);
+            query_pos += usize::from(op.consumes_query());
+            target_pos += usize::from(op.consumes_target());
+        }
+    }
+
+    if query_pos != query.len() || target_pos != end + 1 {
+        return Err(format!("cigar {} does not span query and location", cigar));
+    }
+    if edits != dist {
+        return Err(format!(
+            "cigar {} has {} edits, distance is {}",
+            cigar, edits, dist
+        ));
+    }
+    Ok(())
+}
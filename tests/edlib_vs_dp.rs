@@ -0,0 +1,80 @@
+mod common;
+
+use common::{check_against_reference, reference_align, reference_profile};
+use proptest::prelude::*;
+use rsedlib::{
+    myers::distance_profile,
+    param::{AlignMode, AlignTask, CigarFmt, EdlibAlignParam},
+};
+
+fn seq() -> impl Strategy<Value = Vec<u8>> {
+    prop::collection::vec(prop::sample::select(b"ACGTN".to_vec()), 1..150)
+}
+
+fn eq_pairs() -> impl Strategy<Value = Vec<(u8, u8)>> {
+    let base = || prop::sample::select(b"ACGTN".to_vec());
+    prop::collection::vec((base(), base()), 0..3)
+}
+
+fn aln_param() -> impl Strategy<Value = EdlibAlignParam> {
+    (
+        -1i32..40,
+        prop_oneof![
+            Just(AlignMode::Global),
+            Just(AlignMode::Prefix),
+            Just(AlignMode::Infix),
+            Just(AlignMode::Suffix)
+        ],
+        prop_oneof![
+            Just(AlignTask::Distance),
+            Just(AlignTask::Locations),
+            Just(AlignTask::Path)
+        ],
+    )
+        .prop_map(|(k, mode, task)| {
+            let mut param = EdlibAlignParam::new(k, mode, task);
+            param.set_cigar_fmt(CigarFmt::Extended);
+            param
+        })
+}
+
+proptest! {
+    #[test]
+    fn edlib_matches_reference_dp(
+        query in seq(),
+        target in seq(),
+        eq_pairs in eq_pairs(),
+        mut param in aln_param(),
+    ) {
+        param.set_eq_pairs(eq_pairs.clone());
+        let checked = check_against_reference(&query, &target, &param, &eq_pairs);
+        prop_assert!(checked.is_ok(), "{}", checked.unwrap_err());
+    }
+
+    #[test]
+    fn edlib_finds_planted_query(
+        query in seq(),
+        prefix in seq(),
+        suffix in seq(),
+    ) {
+        let target = [prefix, query.clone(), suffix].concat();
+        let param = EdlibAlignParam::new(-1, AlignMode::Infix, AlignTask::Path);
+        prop_assert_eq!(reference_align(&query, &target, AlignMode::Infix, &[]).0, 0);
+        let checked = check_against_reference(&query, &target, &param, &[]);
+        prop_assert!(checked.is_ok(), "{}", checked.unwrap_err());
+    }
+
+    #[test]
+    fn distance_profile_matches_reference_dp(
+        query in seq(),
+        target in seq(),
+        eq_pairs in eq_pairs(),
+        mut param in aln_param(),
+    ) {
+        param.set_eq_pairs(eq_pairs.clone());
+        prop_assert_eq!(
+            distance_profile(&query, &target, &param),
+            reference_profile(&query, &target, param.mode(), &eq_pairs)
+        );
+    }
+}